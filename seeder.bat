@@ -5,17 +5,31 @@ REM Usage:
 REM   seeder.bat [FAUCET_URL]
 REM Example:
 REM   seeder.bat http://localhost:3010
+REM
+REM Without a URL, the address is derived from faucet-config.toml by the
+REM faucet binary (%FAUCET_BIN%, default target\release\faucet.exe), so the
+REM faucet doesn't have to be running. Otherwise it is read from /status.
+
+if "%FAUCET_BIN%"=="" set "FAUCET_BIN=target\release\faucet.exe"
 
 set "FAUCET_URL=%~1"
+if "%FAUCET_URL%"=="" if exist "%FAUCET_BIN%" if exist faucet-config.toml goto from_config
 if "%FAUCET_URL%"=="" set "FAUCET_URL=http://localhost:3010"
 
 echo Fetching faucet status from %FAUCET_URL%/status ...
 
 for /f "usebackq delims=" %%A in (`powershell -NoProfile -Command "try { (Invoke-RestMethod '%FAUCET_URL%/status').faucet_address } catch { '' }"`) do set "FAUCET_ADDRESS=%%A"
+goto have_address
+
+:from_config
+echo Reading faucet address from faucet-config.toml ...
+for /f "usebackq delims=" %%A in (`"%FAUCET_BIN%" address`) do set "FAUCET_ADDRESS=%%A"
+
+:have_address
 
 if "%FAUCET_ADDRESS%"=="" (
-  echo Failed to read faucet address from %FAUCET_URL%/status
-  echo Is the faucet running and reachable?
+  echo Failed to read the faucet address
+  echo Is the faucet running and reachable, or faucet-config.toml valid?
   exit /b 1
 )
 
@@ -5,19 +5,30 @@ set -euo pipefail
 #   ./seeder.sh [FAUCET_URL]
 # Example:
 #   ./seeder.sh http://localhost:3010
+#
+# Without a URL, the address is derived from faucet-config.toml by the
+# faucet binary ($FAUCET_BIN, default ./target/release/faucet), so the
+# faucet doesn't have to be running. Otherwise it is read from /status.
 
-FAUCET_URL="${1:-http://localhost:3010}"
-STATUS_URL="$FAUCET_URL/status"
+FAUCET_BIN="${FAUCET_BIN:-./target/release/faucet}"
 
-echo "Fetching faucet status from $STATUS_URL ..."
-JSON="$(curl -fsS "$STATUS_URL")"
+if [ -z "${1:-}" ] && [ -x "$FAUCET_BIN" ] && [ -f faucet-config.toml ]; then
+  echo "Reading faucet address from faucet-config.toml ..."
+  FAUCET_ADDRESS="$("$FAUCET_BIN" address)"
+else
+  FAUCET_URL="${1:-http://localhost:3010}"
+  STATUS_URL="$FAUCET_URL/status"
 
-FAUCET_ADDRESS="$(printf '%s' "$JSON" | sed -n 's/.*"faucet_address"[[:space:]]*:[[:space:]]*"\([^"]*\)".*/\1/p')"
+  echo "Fetching faucet status from $STATUS_URL ..."
+  JSON="$(curl -fsS "$STATUS_URL")"
 
-if [ -z "$FAUCET_ADDRESS" ]; then
-  echo "Failed to read faucet address from $STATUS_URL"
-  echo "Response was: $JSON"
-  exit 1
+  FAUCET_ADDRESS="$(printf '%s' "$JSON" | sed -n 's/.*"faucet_address"[[:space:]]*:[[:space:]]*"\([^"]*\)".*/\1/p')"
+
+  if [ -z "$FAUCET_ADDRESS" ]; then
+    echo "Failed to read faucet address from $STATUS_URL"
+    echo "Response was: $JSON"
+    exit 1
+  fi
 fi
 
 echo
@@ -0,0 +1,129 @@
+use kaspa_addresses::Address;
+use kaspa_rpc_core::RpcTransactionId;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{timeout_at, Duration, Instant};
+use tracing::{error, info};
+
+use crate::error::FaucetError;
+use crate::ledger::unix_now;
+use crate::Faucet;
+
+/// Claims buffered beyond this many are refused instead of queued.
+const QUEUE_CAPACITY: usize = 1024;
+
+struct PendingClaim {
+    destination: Address,
+    amount: u64,
+    reply: oneshot::Sender<Result<RpcTransactionId, FaucetError>>,
+}
+
+/// Front end of the batching worker. Claims pushed here are paid together
+/// with whatever else arrives within the batch window.
+#[derive(Clone)]
+pub struct ClaimQueue {
+    sender: mpsc::Sender<PendingClaim>,
+    /// Suggested wait when the queue is full: about one batch window.
+    retry_after_seconds: u64,
+}
+
+pub struct ClaimReceiver(mpsc::Receiver<PendingClaim>);
+
+impl ClaimQueue {
+    pub fn new(window: Duration) -> (Self, ClaimReceiver) {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let queue = Self {
+            sender,
+            retry_after_seconds: window.as_secs().max(1),
+        };
+        (queue, ClaimReceiver(receiver))
+    }
+
+    /// Queues a payment and waits for the id of the transaction that carries it.
+    pub async fn submit(&self, destination: Address, amount: u64) -> Result<RpcTransactionId, FaucetError> {
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .try_send(PendingClaim {
+                destination,
+                amount,
+                reply,
+            })
+            .map_err(|e| match e {
+                TrySendError::Full(_) => FaucetError::QueueFull {
+                    retry_after_seconds: self.retry_after_seconds,
+                },
+                TrySendError::Closed(_) => FaucetError::Internal("Claim queue unavailable".to_string()),
+            })?;
+        response
+            .await
+            .map_err(|_| FaucetError::Internal("Claim queue dropped the request".to_string()))?
+    }
+}
+
+/// Collects claims for up to `window` (or until `max_claims` are waiting)
+/// and pays each batch from a single transaction.
+pub fn spawn_worker(faucet: Faucet, receiver: ClaimReceiver, window: Duration, max_claims: usize) -> JoinHandle<()> {
+    let ClaimReceiver(mut receiver) = receiver;
+    tokio::spawn(async move {
+        while let Some(first) = receiver.recv().await {
+            let queued_at = unix_now();
+            let deadline = Instant::now() + window;
+            let mut batch = vec![first];
+            while batch.len() < max_claims {
+                match timeout_at(deadline, receiver.recv()).await {
+                    Ok(Some(claim)) => batch.push(claim),
+                    _ => break,
+                }
+            }
+
+            // Split batches that don't fit in one transaction, paying the first half first
+            let mut pending = vec![batch];
+            while let Some(mut batch) = pending.pop() {
+                match pay(&faucet, &batch, queued_at).await {
+                    Err(FaucetError::TransactionTooLarge(_) | FaucetError::AmountTooSmall(_)) if batch.len() > 1 => {
+                        info!("Batch of {} claims is over the mass limit, splitting it", batch.len());
+                        let second = batch.split_off(batch.len() / 2);
+                        pending.push(second);
+                        pending.push(batch);
+                    }
+                    result => {
+                        for claim in batch {
+                            // The caller may have gone away; the payment went out regardless
+                            let _ = claim.reply.send(result.clone());
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Pays `batch` from a single transaction.
+async fn pay(faucet: &Faucet, batch: &[PendingClaim], queued_at: u64) -> Result<RpcTransactionId, FaucetError> {
+    let payments = batch
+        .iter()
+        .map(|c| (c.destination.clone(), c.amount))
+        .collect::<Vec<_>>();
+    let mut signed_tx_id = None;
+    let result = crate::submit_faucet_transaction(faucet, &payments, |tx_id| {
+        faucet.tracker.submitting(tx_id, queued_at);
+        signed_tx_id = Some(tx_id);
+    })
+    .await;
+
+    match result {
+        Ok(tx_id) => {
+            info!("Paid {} claims in transaction {}", batch.len(), tx_id);
+            faucet.tracker.submitted(tx_id);
+            Ok(tx_id)
+        }
+        Err(e) => {
+            error!("Batch of {} claims failed: {e}", batch.len());
+            if let Some(tx_id) = signed_tx_id {
+                faucet.tracker.rejected(tx_id, e.to_string());
+            }
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use kaspa_consensus_core::{
+    subnets::SUBNETWORK_ID_NATIVE,
+    tx::{
+        ScriptPublicKey, Transaction, TransactionInput, TransactionOutpoint, TransactionOutput,
+        UtxoEntry,
+    },
+};
+use std::fmt;
+
+use crate::fees::{FeeCalculator, MAXIMUM_STANDARD_TRANSACTION_MASS};
+use crate::utxo::Utxo;
+
+/// Length of a Schnorr P2PK signature script (push opcode, 64-byte signature, sighash type).
+const SCHNORR_SIGNATURE_SCRIPT_LEN: usize = 66;
+
+/// Change below this is folded into the fee instead of creating an output.
+const DUST_SOMPI: u64 = 1000;
+
+/// Rounds allowed for the change amount to settle; each round can shift
+/// the storage mass and therefore the fee.
+const MAX_FEE_ROUNDS: usize = 8;
+
+#[derive(Debug)]
+pub enum BuildError {
+    Insufficient { have: u64, need: u64 },
+    TooLarge { mass: u64, kind: MassKind },
+}
+
+/// Which mass put a transaction over the standard limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MassKind {
+    /// Too many inputs (or outputs) for the transaction's size.
+    Compute,
+    /// Outputs too small for their value: storage mass grows as outputs shrink.
+    Storage,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::Insufficient { have, need } => {
+                write!(f, "Insufficient faucet funds. Have {have} sompi, need {need} sompi")
+            }
+            BuildError::TooLarge {
+                mass,
+                kind: MassKind::Compute,
+            } => write!(
+                f,
+                "Transaction mass {mass} exceeds the standard limit of {MAXIMUM_STANDARD_TRANSACTION_MASS}; too many inputs, consolidate faucet UTXOs"
+            ),
+            BuildError::TooLarge {
+                mass,
+                kind: MassKind::Storage,
+            } => write!(
+                f,
+                "Transaction storage mass {mass} exceeds the standard limit of {MAXIMUM_STANDARD_TRANSACTION_MASS}; the payment amounts are too small, raise them"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// A fully priced, not yet signed transaction.
+pub struct UnsignedTransaction {
+    pub tx: Transaction,
+    pub entries: Vec<UtxoEntry>,
+    pub fee: u64,
+    /// Index and entry-to-be of the change output, if one was added.
+    pub change: Option<(u32, TransactionOutput)>,
+}
+
+/// Builds a transaction spending `inputs` to `payments`, sending whatever is
+/// left after the mass-based fee back to `change_script`.
+pub fn build_transaction(
+    inputs: &[Utxo],
+    payments: &[TransactionOutput],
+    change_script: &ScriptPublicKey,
+    fees: &FeeCalculator,
+) -> Result<UnsignedTransaction, BuildError> {
+    let total_in: u64 = inputs.iter().map(|u| u.entry.amount).sum();
+    let total_out: u64 = payments.iter().map(|o| o.value).sum();
+    let entries = inputs.iter().map(|u| u.entry.clone()).collect::<Vec<_>>();
+    if total_in < total_out {
+        return Err(BuildError::Insufficient {
+            have: total_in,
+            need: total_out,
+        });
+    }
+
+    // Try with a change output first, letting the change settle against the fee
+    let mut change = total_in - total_out;
+    for _ in 0..MAX_FEE_ROUNDS {
+        if change < DUST_SOMPI {
+            break;
+        }
+        let mut outputs = payments.to_vec();
+        outputs.push(TransactionOutput::new(change, change_script.clone()));
+        let draft = assemble(inputs, outputs.clone(), SCHNORR_SIGNATURE_SCRIPT_LEN);
+        let mass = fees.mass(&draft, &entries);
+        let fee = fees.fee(mass);
+        if mass > MAXIMUM_STANDARD_TRANSACTION_MASS && change_pays_for_itself(change, fees) {
+            // Dropping the change would only shave the mass by burning it
+            return Err(too_large(&draft, mass, fees));
+        }
+        if mass > MAXIMUM_STANDARD_TRANSACTION_MASS || total_in < total_out.saturating_add(fee) {
+            break;
+        }
+        let settled = total_in - total_out - fee;
+        if settled >= change {
+            let change_output = outputs.last().cloned().map(|o| (payments.len() as u32, o));
+            return Ok(UnsignedTransaction {
+                tx: assemble(inputs, outputs, 0),
+                entries,
+                fee: total_in - total_out - change,
+                change: change_output,
+            });
+        }
+        change = settled;
+    }
+
+    // Otherwise the change is dust or not worth its storage mass, and
+    // everything above the payments goes to the fee
+    let draft = assemble(inputs, payments.to_vec(), SCHNORR_SIGNATURE_SCRIPT_LEN);
+    let mass = fees.mass(&draft, &entries);
+    if mass > MAXIMUM_STANDARD_TRANSACTION_MASS {
+        return Err(too_large(&draft, mass, fees));
+    }
+    let fee = fees.fee(mass);
+    if total_in < total_out.saturating_add(fee) {
+        return Err(BuildError::Insufficient {
+            have: total_in,
+            need: total_out.saturating_add(fee),
+        });
+    }
+    Ok(UnsignedTransaction {
+        tx: assemble(inputs, payments.to_vec(), 0),
+        entries,
+        fee: total_in - total_out,
+        change: None,
+    })
+}
+
+/// `TooLarge` for `draft`, blaming compute mass if that alone is over the limit.
+fn too_large(draft: &Transaction, mass: u64, fees: &FeeCalculator) -> BuildError {
+    let kind = if fees.compute_mass(draft) > MAXIMUM_STANDARD_TRANSACTION_MASS {
+        MassKind::Compute
+    } else {
+        MassKind::Storage
+    };
+    BuildError::TooLarge { mass, kind }
+}
+
+/// Whether a change output of `change` sompi is worth having: its own
+/// storage mass fits in a transaction and costs less than the change itself.
+fn change_pays_for_itself(change: u64, fees: &FeeCalculator) -> bool {
+    let storage_mass = fees.output_storage_mass(change);
+    storage_mass <= MAXIMUM_STANDARD_TRANSACTION_MASS && fees.fee(storage_mass) < change
+}
+
+/// Greedily adds `candidates` in order until a transaction paying `payments`
+/// can be built.
+pub fn select_greedy(
+    candidates: Vec<Utxo>,
+    payments: &[TransactionOutput],
+    change_script: &ScriptPublicKey,
+    fees: &FeeCalculator,
+) -> Result<Vec<Utxo>, BuildError> {
+    let total_out: u64 = payments.iter().map(|o| o.value).sum();
+    let mut selected = Vec::new();
+    let mut total_in: u64 = 0;
+    let mut need = total_out;
+    let mut too_large = None;
+
+    for utxo in candidates {
+        total_in = total_in.saturating_add(utxo.entry.amount);
+        selected.push(utxo);
+        if total_in < need {
+            continue;
+        }
+        match build_transaction(&selected, payments, change_script, fees) {
+            Ok(_) => return Ok(selected),
+            Err(BuildError::Insufficient { need: n, .. }) => need = n,
+            // More inputs only add compute mass
+            Err(e @ BuildError::TooLarge {
+                kind: MassKind::Compute,
+                ..
+            }) => return Err(too_large.unwrap_or(e)),
+            // but they grow the change and offset storage mass, so keep going
+            Err(e @ BuildError::TooLarge {
+                kind: MassKind::Storage,
+                ..
+            }) => too_large = Some(e),
+        }
+    }
+
+    Err(too_large.unwrap_or(BuildError::Insufficient {
+        have: total_in,
+        need,
+    }))
+}
+
+/// Fee for the compute mass of a transaction with `inputs` signed inputs
+/// paying `outputs`. Storage mass is ignored, so this is a lower bound.
+pub fn compute_fee(inputs: usize, outputs: &[TransactionOutput], fees: &FeeCalculator) -> u64 {
+    let inputs = (0..inputs)
+        .map(|i| {
+            let outpoint = TransactionOutpoint::new(Default::default(), i as u32);
+            TransactionInput::new(outpoint, vec![0; SCHNORR_SIGNATURE_SCRIPT_LEN], i as u64, 1)
+        })
+        .collect::<Vec<_>>();
+    let tx = Transaction::new(0, inputs, outputs.to_vec(), 0, SUBNETWORK_ID_NATIVE, 0, vec![]);
+    fees.fee(fees.compute_mass(&tx))
+}
+
+/// Assembles the transaction, filling signature scripts with
+/// `signature_script_len` placeholder bytes (used for mass estimation).
+fn assemble(inputs: &[Utxo], outputs: Vec<TransactionOutput>, signature_script_len: usize) -> Transaction {
+    let inputs = inputs
+        .iter()
+        .enumerate()
+        .map(|(i, u)| TransactionInput::new(u.outpoint, vec![0; signature_script_len], i as u64, 1))
+        .collect::<Vec<_>>();
+    Transaction::new(0, inputs, outputs, 0, SUBNETWORK_ID_NATIVE, 0, vec![])
+}
@@ -0,0 +1,185 @@
+use kaspa_consensus_core::tx::{ScriptPublicKey, TransactionOutput};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::sync::Arc;
+
+use crate::builder::{self, BuildError};
+use crate::fees::FeeCalculator;
+use crate::utxo::Utxo;
+
+/// Upper bound on subsets explored by branch-and-bound before giving up.
+const BNB_MAX_TRIES: usize = 100_000;
+
+/// Picks the inputs for a transaction paying `payments`.
+pub trait CoinSelector: Send + Sync {
+    fn select(
+        &self,
+        candidates: Vec<Utxo>,
+        payments: &[TransactionOutput],
+        change_script: &ScriptPublicKey,
+        fees: &FeeCalculator,
+    ) -> Result<Vec<Utxo>, BuildError>;
+}
+
+/// Strategy names accepted by the `coin_selection` config field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CoinSelection {
+    #[default]
+    LargestFirst,
+    SmallestFirst,
+    BranchAndBound,
+    OldestFirst,
+}
+
+impl CoinSelection {
+    pub fn selector(self) -> Arc<dyn CoinSelector> {
+        match self {
+            CoinSelection::LargestFirst => Arc::new(LargestFirst),
+            CoinSelection::SmallestFirst => Arc::new(SmallestFirst),
+            CoinSelection::BranchAndBound => Arc::new(BranchAndBound),
+            CoinSelection::OldestFirst => Arc::new(OldestFirst),
+        }
+    }
+}
+
+/// Spends the biggest UTXOs first, keeping input counts (and fees) low.
+pub struct LargestFirst;
+
+impl CoinSelector for LargestFirst {
+    fn select(
+        &self,
+        mut candidates: Vec<Utxo>,
+        payments: &[TransactionOutput],
+        change_script: &ScriptPublicKey,
+        fees: &FeeCalculator,
+    ) -> Result<Vec<Utxo>, BuildError> {
+        candidates.sort_by_key(|u| Reverse(u.entry.amount));
+        builder::select_greedy(candidates, payments, change_script, fees)
+    }
+}
+
+/// Spends the smallest UTXOs first, steadily cleaning up dust as claims go out.
+pub struct SmallestFirst;
+
+impl CoinSelector for SmallestFirst {
+    fn select(
+        &self,
+        mut candidates: Vec<Utxo>,
+        payments: &[TransactionOutput],
+        change_script: &ScriptPublicKey,
+        fees: &FeeCalculator,
+    ) -> Result<Vec<Utxo>, BuildError> {
+        candidates.sort_by_key(|u| u.entry.amount);
+        builder::select_greedy(candidates, payments, change_script, fees)
+    }
+}
+
+/// Spends the UTXOs with the lowest DAA score first.
+pub struct OldestFirst;
+
+impl CoinSelector for OldestFirst {
+    fn select(
+        &self,
+        mut candidates: Vec<Utxo>,
+        payments: &[TransactionOutput],
+        change_script: &ScriptPublicKey,
+        fees: &FeeCalculator,
+    ) -> Result<Vec<Utxo>, BuildError> {
+        candidates.sort_by_key(|u| u.entry.block_daa_score);
+        builder::select_greedy(candidates, payments, change_script, fees)
+    }
+}
+
+/// Looks for a set of inputs that covers the payments and fee closely
+/// enough that no change output is needed, falling back to largest-first.
+pub struct BranchAndBound;
+
+impl CoinSelector for BranchAndBound {
+    fn select(
+        &self,
+        mut candidates: Vec<Utxo>,
+        payments: &[TransactionOutput],
+        change_script: &ScriptPublicKey,
+        fees: &FeeCalculator,
+    ) -> Result<Vec<Utxo>, BuildError> {
+        let total_out: u64 = payments.iter().map(|o| o.value).sum();
+        let base_fee = builder::compute_fee(0, payments, fees);
+        let input_fee = builder::compute_fee(1, payments, fees).saturating_sub(base_fee);
+        let mut with_change = payments.to_vec();
+        with_change.push(TransactionOutput::new(0, change_script.clone()));
+        let change_fee = builder::compute_fee(0, &with_change, fees).saturating_sub(base_fee);
+
+        // Excess below the cost of creating and later spending a change output is left to the fee
+        let target = total_out.saturating_add(base_fee);
+        let cost_of_change = change_fee.saturating_add(input_fee);
+
+        candidates.retain(|u| u.entry.amount > input_fee);
+        candidates.sort_by_key(|u| Reverse(u.entry.amount));
+        let values = candidates
+            .iter()
+            .map(|u| u.entry.amount - input_fee)
+            .collect::<Vec<_>>();
+
+        if let Some(indices) = branch_and_bound(&values, target, target.saturating_add(cost_of_change)) {
+            let selected = indices
+                .into_iter()
+                .map(|i| candidates[i].clone())
+                .collect::<Vec<_>>();
+            // Storage mass isn't part of the estimate, so confirm with the real builder
+            if builder::build_transaction(&selected, payments, change_script, fees).is_ok() {
+                return Ok(selected);
+            }
+        }
+
+        LargestFirst.select(candidates, payments, change_script, fees)
+    }
+}
+
+/// Depth-first search for a subset of `values` (sorted descending) whose sum
+/// lies in `[target, upper]`. Returns the indices of the first match found.
+fn branch_and_bound(values: &[u64], target: u64, upper: u64) -> Option<Vec<usize>> {
+    // remaining[i] = sum of values[i..], used to prune branches that can't reach the target
+    let mut remaining = vec![0u64; values.len() + 1];
+    for i in (0..values.len()).rev() {
+        remaining[i] = remaining[i + 1].saturating_add(values[i]);
+    }
+
+    let mut selection = Vec::new();
+    let mut tries = 0;
+    if search(values, &remaining, 0, 0, target, upper, &mut selection, &mut tries) {
+        Some(selection)
+    } else {
+        None
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn search(
+    values: &[u64],
+    remaining: &[u64],
+    index: usize,
+    sum: u64,
+    target: u64,
+    upper: u64,
+    selection: &mut Vec<usize>,
+    tries: &mut usize,
+) -> bool {
+    *tries += 1;
+    if *tries > BNB_MAX_TRIES || sum > upper {
+        return false;
+    }
+    if sum >= target {
+        return true;
+    }
+    if index == values.len() || sum.saturating_add(remaining[index]) < target {
+        return false;
+    }
+
+    selection.push(index);
+    if search(values, remaining, index + 1, sum.saturating_add(values[index]), target, upper, selection, tries) {
+        return true;
+    }
+    selection.pop();
+    search(values, remaining, index + 1, sum, target, upper, selection, tries)
+}
@@ -1,7 +1,24 @@
+use kaspa_addresses::Address;
+use kaspa_consensus_core::{
+    config::params::Params,
+    network::{NetworkId, NetworkType},
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs;
 
-fn parse_kas_to_sompi(s: &str) -> Result<u64, String> {
+use crate::coin_selection::CoinSelection;
+use crate::fees::FeePriority;
+use crate::keys;
+use crate::rpc::{Endpoint, Transport};
+
+pub const CONFIG_PATH: &str = "faucet-config.toml";
+
+/// First path segments the server routes itself, so no faucet can be mounted there.
+const RESERVED_PATHS: [&str; 2] = ["faucets", "static"];
+
+/// Parses a KAS amount such as "1.5" into sompi.
+pub fn parse_kas_to_sompi(s: &str) -> Result<u64, String> {
     const SOMPI_PER_KAS: u64 = 100_000_000;
     let raw = s.trim();
     if raw.is_empty() {
@@ -43,7 +60,7 @@ fn parse_kas_to_sompi(s: &str) -> Result<u64, String> {
         .ok_or_else(|| "amount overflows u64".to_string())
 }
 
-fn deserialize_amount_per_claim<'de, D>(deserializer: D) -> Result<u64, D::Error>
+fn deserialize_kas_amount<'de, D>(deserializer: D) -> Result<u64, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
@@ -61,7 +78,7 @@ where
         AmountField::KasFloat(f) => {
             if !f.is_finite() || f < 0.0 {
                 return Err(serde::de::Error::custom(
-                    "amount_per_claim must be a finite number >= 0",
+                    "amount must be a finite number >= 0",
                 ));
             }
             let s = format!("{:.8}", f);
@@ -70,14 +87,14 @@ where
         AmountField::KasString(s) => {
             let raw = s.trim();
             if raw.is_empty() {
-                return Err(serde::de::Error::custom("amount_per_claim is empty"));
+                return Err(serde::de::Error::custom("amount is empty"));
             }
             if raw.chars().any(|c| c == '.') {
                 parse_kas_to_sompi(raw).map_err(serde::de::Error::custom)
             } else {
                 raw.parse::<u64>().map_err(|_| {
                     serde::de::Error::custom(
-                        "amount_per_claim must be a u64 sompi integer or a KAS decimal string",
+                        "amount must be a u64 sompi integer or a KAS decimal string",
                     )
                 })
             }
@@ -85,40 +102,306 @@ where
     }
 }
 
+fn deserialize_one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    let urls = match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(url) => vec![url],
+        OneOrMany::Many(urls) => urls,
+    };
+    if urls.is_empty() {
+        return Err(serde::de::Error::custom("at least one kaspad URL is required"));
+    }
+    Ok(urls)
+}
+
+/// Top-level config. Faucets are listed as `[[faucets]]` tables; a file
+/// without any is read as a single faucet mounted at `/`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    pub kaspad_url: String,
+    #[serde(default = "default_port")]
     pub port: u16,
+    #[serde(default)]
+    pub faucets: Vec<FaucetConfig>,
+}
+
+/// One faucet: its node, wallet and claim policy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaucetConfig {
+    /// Path prefix the faucet is served under, e.g. "tn12" for `/tn12/claim`.
+    /// Empty mounts it at the root; only one faucet may do so.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub path: String,
+    /// Network the faucet pays on: "testnet-12", "testnet-10", "devnet", "simnet", ...
+    /// kaspad must report the same id.
+    #[serde(default = "default_network")]
+    pub network: NetworkId,
+    /// kaspad endpoints, as one URL or a list to fail over between.
+    /// The network's default port for the transport is used when none is given.
+    #[serde(alias = "kaspad_url", deserialize_with = "deserialize_one_or_many")]
+    pub kaspad_urls: Vec<String>,
+    /// "grpc", "wrpc-borsh" or "wrpc-json". When unset, `ws://` and `wss://`
+    /// URLs use wRPC (Borsh) and everything else gRPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transport: Option<Transport>,
+    /// How often every endpoint is probed for reachability, network and sync state.
+    #[serde(default = "default_health_check_interval_seconds")]
+    pub health_check_interval_seconds: u64,
+    /// Hex private key. Leave empty and set `faucet_private_key_file` to keep it out of the config.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub faucet_private_key: String,
-    #[serde(deserialize_with = "deserialize_amount_per_claim")]
+    /// File holding the hex private key, e.g. one written by `faucet init --key-file`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub faucet_private_key_file: Option<String>,
+    #[serde(deserialize_with = "deserialize_kas_amount")]
     pub amount_per_claim: u64,
     pub claim_interval_seconds: u64,
+    #[serde(default = "default_address_claim_interval_seconds")]
+    pub address_claim_interval_seconds: u64,
+    #[serde(default = "default_ledger_path")]
+    pub ledger_path: String,
+    /// Fee estimate bucket to pay: "priority", "normal" or "low".
+    #[serde(default)]
+    pub fee_priority: FeePriority,
+    /// "largest-first", "smallest-first", "branch-and-bound" or "oldest-first".
+    #[serde(default)]
+    pub coin_selection: CoinSelection,
+    /// DAA score a coinbase output must age before it can be spent. Defaults
+    /// to the network's consensus value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coinbase_maturity: Option<u64>,
+    /// Blue score on top of the accepting block before a claim counts as confirmed.
+    #[serde(default = "default_confirmation_depth")]
+    pub confirmation_depth: u64,
+    /// How long to gather claims before paying them in one transaction.
+    #[serde(default = "default_batch_window_ms")]
+    pub batch_window_ms: u64,
+    /// A batch is sent early once this many claims are waiting. Batches over
+    /// the mass limit are split, so this doesn't need to account for mass.
+    #[serde(default = "default_batch_max_claims")]
+    pub batch_max_claims: usize,
+    #[serde(default)]
+    pub consolidation: ConsolidationConfig,
+}
+
+/// Background merging of small UTXOs, configured under `[consolidation]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsolidationConfig {
+    pub enabled: bool,
+    pub interval_seconds: u64,
+    /// UTXOs below this amount (sompi or KAS decimal) are merged.
+    #[serde(deserialize_with = "deserialize_kas_amount")]
+    pub utxo_threshold: u64,
+    /// Don't bother until at least this many small UTXOs have piled up.
+    pub min_utxos: usize,
+    /// Upper bound on inputs per consolidation transaction; the mass limit may cap it lower.
+    pub max_inputs: usize,
 }
 
-impl Default for Config {
+impl Default for ConsolidationConfig {
     fn default() -> Self {
         Self {
-            kaspad_url: "127.0.0.1:16210".to_string(),
-            port: 3010,
+            enabled: true,
+            interval_seconds: 600,
+            utxo_threshold: 10 * 100_000_000, // 10 KAS
+            min_utxos: 50,
+            max_inputs: 80,
+        }
+    }
+}
+
+fn default_port() -> u16 {
+    3010
+}
+
+fn default_network() -> NetworkId {
+    NetworkId::with_suffix(NetworkType::Testnet, 12)
+}
+
+fn default_health_check_interval_seconds() -> u64 {
+    10
+}
+
+fn default_address_claim_interval_seconds() -> u64 {
+    3600
+}
+
+fn default_confirmation_depth() -> u64 {
+    10
+}
+
+fn default_batch_window_ms() -> u64 {
+    2000
+}
+
+fn default_batch_max_claims() -> usize {
+    20
+}
+
+fn default_ledger_path() -> String {
+    "faucet-ledger.sqlite".to_string()
+}
+
+impl Default for FaucetConfig {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            network: default_network(),
+            kaspad_urls: vec!["127.0.0.1".to_string()],
+            transport: None,
+            health_check_interval_seconds: default_health_check_interval_seconds(),
             faucet_private_key: String::new(),
-            amount_per_claim: 100_000_000, // 0.001 KAS in sompis
+            faucet_private_key_file: None,
+            amount_per_claim: 100_000_000, // 1 KAS in sompis
             claim_interval_seconds: 3600, // 1 hour
+            address_claim_interval_seconds: default_address_claim_interval_seconds(),
+            ledger_path: default_ledger_path(),
+            fee_priority: FeePriority::default(),
+            coin_selection: CoinSelection::default(),
+            coinbase_maturity: None,
+            confirmation_depth: default_confirmation_depth(),
+            batch_window_ms: default_batch_window_ms(),
+            batch_max_claims: default_batch_max_claims(),
+            consolidation: ConsolidationConfig::default(),
         }
     }
 }
 
 impl Config {
     pub fn load() -> anyhow::Result<Self> {
-        let config_path = "faucet-config.toml";
+        let config_path = CONFIG_PATH;
         if !std::path::Path::new(config_path).exists() {
-            let default = Config::default();
-            let toml = toml::to_string_pretty(&default)?;
-            fs::write(config_path, toml)?;
-            anyhow::bail!("Created default config at {}. Please edit and restart.", config_path);
+            let address = Self::init(config_path, default_network(), None)?;
+            anyhow::bail!(
+                "Created default config at {} with a new faucet key for {}. Fund that address, check the settings and restart.",
+                config_path,
+                address
+            );
         }
 
         let contents = fs::read_to_string(config_path)?;
-        let config: Config = toml::from_str(&contents)?;
+        let mut config: Config = toml::from_str(&contents)?;
+        if config.faucets.is_empty() {
+            config.faucets.push(toml::from_str(&contents)?);
+        }
+        config.validate()?;
         Ok(config)
     }
+
+    /// Writes a config for one faucet on `network` with a freshly generated
+    /// key, kept inline or in `key_file`. Both are created readable by the
+    /// owner only, and neither is overwritten. Returns the faucet address.
+    pub fn init(config_path: &str, network: NetworkId, key_file: Option<&str>) -> anyhow::Result<Address> {
+        if std::path::Path::new(config_path).exists() {
+            anyhow::bail!("{} already exists", config_path);
+        }
+        let key = keys::generate();
+        let mut faucet = FaucetConfig {
+            network,
+            ..Default::default()
+        };
+        match key_file {
+            Some(key_file) => {
+                keys::write_secret(key_file, &keys::to_hex(&key))?;
+                faucet.faucet_private_key_file = Some(key_file.to_string());
+            }
+            None => faucet.faucet_private_key = keys::to_hex(&key),
+        }
+        let toml = format!("port = {}\n{}", default_port(), toml::to_string_pretty(&faucet)?);
+        keys::write_secret(config_path, &toml)?;
+        Ok(keys::address(&key, network))
+    }
+
+    fn validate(&mut self) -> anyhow::Result<()> {
+        let mut paths = HashSet::new();
+        let mut ledgers = HashSet::new();
+        let mut keys_in_use = HashSet::new();
+        for faucet in &mut self.faucets {
+            faucet.path = faucet.path.trim_matches('/').to_string();
+            let first = faucet.path.split('/').next().unwrap_or_default();
+            if RESERVED_PATHS.contains(&first) {
+                anyhow::bail!("Faucet path \"/{}\" is taken by the server's own \"/{first}\" route", faucet.path);
+            }
+            if faucet.path.contains(['{', '}', '*']) {
+                anyhow::bail!("Faucet path \"/{}\" must not contain '{{', '}}' or '*'", faucet.path);
+            }
+            if !paths.insert(faucet.path.clone()) {
+                anyhow::bail!("Two faucets are mounted at path \"/{}\"", faucet.path);
+            }
+            if faucet.amount_per_claim == 0 {
+                anyhow::bail!("amount_per_claim must be more than 0");
+            }
+            // Rate limits are per ledger, so sharing one would mix up faucets
+            if !ledgers.insert(faucet.ledger_path.clone()) {
+                anyhow::bail!("Faucets must not share ledger_path \"{}\"", faucet.ledger_path);
+            }
+            if let Some(key_file) = &faucet.faucet_private_key_file {
+                if !faucet.faucet_private_key.is_empty() {
+                    anyhow::bail!("Set either faucet_private_key or faucet_private_key_file, not both");
+                }
+                faucet.faucet_private_key = keys::read_key_file(key_file)?;
+            }
+            // Each faucet tracks its own UTXOs, so two with one key would double-spend
+            // each other. Keys that don't parse are reported when the faucet starts.
+            if let Ok(key) = keys::parse(&faucet.faucet_private_key) {
+                if !keys_in_use.insert((key.secret_bytes(), faucet.network)) {
+                    anyhow::bail!("Faucets on {} must not share a private key", faucet.network);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FaucetConfig {
+    /// `coinbase_maturity`, or the network's consensus value when unset.
+    pub fn coinbase_maturity(&self) -> u64 {
+        self.coinbase_maturity.unwrap_or_else(|| Params::from(self.network).coinbase_maturity().after())
+    }
+
+    /// `kaspad_urls` with their transport, normalized to a `grpc://`, `ws://`
+    /// or `wss://` URL with the network's default port filled in.
+    pub fn endpoints(&self) -> Vec<Endpoint> {
+        self.kaspad_urls
+            .iter()
+            .map(|url| {
+                let secure = url.starts_with("wss://");
+                let transport = self.transport.unwrap_or(if url.starts_with("ws://") || secure {
+                    Transport::WrpcBorsh
+                } else {
+                    Transport::Grpc
+                });
+                let address = url
+                    .trim_start_matches("grpc://")
+                    .trim_start_matches("http://")
+                    .trim_start_matches("https://")
+                    .trim_start_matches("ws://")
+                    .trim_start_matches("wss://")
+                    .trim_end_matches('/');
+                let has_port = address.rsplit_once(':').is_some_and(|(host, port)| {
+                    port.parse::<u16>().is_ok() && (!host.contains(':') || host.ends_with(']'))
+                });
+                let (scheme, default_port) = match transport {
+                    Transport::Grpc => ("grpc", self.network.default_rpc_port()),
+                    Transport::WrpcBorsh => (if secure { "wss" } else { "ws" }, self.network.default_borsh_rpc_port()),
+                    Transport::WrpcJson => (if secure { "wss" } else { "ws" }, self.network.default_json_rpc_port()),
+                };
+                let url = if has_port {
+                    format!("{scheme}://{address}")
+                } else {
+                    format!("{scheme}://{address}:{default_port}")
+                };
+                Endpoint { url, transport }
+            })
+            .collect()
+    }
 }
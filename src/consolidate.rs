@@ -0,0 +1,87 @@
+use kaspa_rpc_core::RpcTransactionId;
+use kaspa_txscript::standard::pay_to_address_script;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{info, warn};
+
+use crate::builder::{self, BuildError};
+use crate::config::ConsolidationConfig;
+use crate::fees::FeeCalculator;
+use crate::Faucet;
+
+/// Periodically merges small faucet UTXOs (typically coinbase outputs from
+/// mining to the faucet address) into a single output.
+pub fn spawn(faucet: Faucet, config: ConsolidationConfig) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(config.interval_seconds.max(1)));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match consolidate(&faucet, &config).await {
+                Ok(Some(tx_id)) => info!("Submitted consolidation transaction {}", tx_id),
+                Ok(None) => {}
+                Err(e) => warn!("UTXO consolidation failed: {e:#}"),
+            }
+        }
+    })
+}
+
+/// Runs one consolidation round. Returns `None` when there are too few small
+/// UTXOs to be worth merging.
+pub async fn consolidate(
+    faucet: &Faucet,
+    config: &ConsolidationConfig,
+) -> anyhow::Result<Option<RpcTransactionId>> {
+    let client = faucet.nodes.client();
+    let fees = FeeCalculator::fetch(client.as_ref(), faucet.network, faucet.fee_priority).await?;
+    let change_script = pay_to_address_script(&faucet.faucet_address);
+
+    // Only unreserved UTXOs are offered, so pending claims are never touched
+    let reservation = faucet.utxo_manager.reserve(|spendable| -> anyhow::Result<_> {
+        let mut small = spendable
+            .into_iter()
+            .filter(|u| u.entry.amount < config.utxo_threshold)
+            .collect::<Vec<_>>();
+        if small.len() < config.min_utxos.max(2) {
+            return Ok(Vec::new());
+        }
+        small.sort_by_key(|u| u.entry.amount);
+
+        // Add inputs until the next one would push the transaction over the
+        // mass limit, keeping the most that still yields a merged output
+        let mut selected = Vec::new();
+        let mut with_output = 0;
+        for utxo in small.into_iter().take(config.max_inputs) {
+            selected.push(utxo);
+            match builder::build_transaction(&selected, &[], &change_script, &fees) {
+                Ok(unsigned) if unsigned.change.is_some() => with_output = selected.len(),
+                Err(BuildError::TooLarge { .. }) => break,
+                _ => {}
+            }
+        }
+        selected.truncate(with_output);
+        Ok(selected)
+    })?;
+
+    if reservation.utxos().len() < 2 {
+        return Ok(None);
+    }
+
+    let unsigned = builder::build_transaction(reservation.utxos(), &[], &change_script, &fees)?;
+    if unsigned.tx.outputs.is_empty() {
+        anyhow::bail!(
+            "Consolidating {} UTXOs would pay them all as fee ({} sompi); not submitting",
+            reservation.utxos().len(),
+            unsigned.fee
+        );
+    }
+    let tx_id = crate::sign_and_submit(
+        client.as_ref(),
+        reservation,
+        unsigned,
+        &faucet.faucet_private_key,
+        |_| {},
+    )
+    .await?;
+    Ok(Some(tx_id))
+}
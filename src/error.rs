@@ -0,0 +1,188 @@
+use axum::{
+    extract::rejection::JsonRejection,
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use kaspa_addresses::Prefix;
+use serde::Serialize;
+use std::fmt;
+
+use crate::builder::{BuildError, MassKind};
+use crate::rate_limiter::{Limit, TryClaimError};
+
+/// Errors surfaced to HTTP clients. Each maps to a stable machine-readable
+/// `code` in the JSON body.
+#[derive(Debug, Clone)]
+pub enum FaucetError {
+    /// The request body is missing, not JSON or not the expected shape.
+    InvalidRequest(String),
+    InvalidAddress(String),
+    WrongNetwork { expected: Prefix, found: Prefix },
+    InvalidTransactionId(String),
+    ClaimNotFound(String),
+    RateLimitedIp { retry_after_seconds: u64 },
+    RateLimitedAddress { retry_after_seconds: u64 },
+    InsufficientFunds(String),
+    /// Too many claims are waiting for a batch.
+    QueueFull { retry_after_seconds: u64 },
+    /// The transaction needs too many inputs; consolidating UTXOs helps.
+    TransactionTooLarge(String),
+    /// Payment outputs are too small to carry their storage mass.
+    AmountTooSmall(String),
+    NodeUnavailable(String),
+    /// The connection to kaspad dropped and is being re-established.
+    NodeDisconnected { retry_after_seconds: u64 },
+    /// kaspad is still syncing (e.g. in IBD), so transactions can't be trusted to land.
+    NodeNotSynced,
+    SubmitRejected(String),
+    Internal(String),
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_after_seconds: Option<u64>,
+}
+
+impl FaucetError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            FaucetError::InvalidRequest(_) => "invalid_request",
+            FaucetError::InvalidAddress(_) => "invalid_address",
+            FaucetError::WrongNetwork { .. } => "wrong_network",
+            FaucetError::InvalidTransactionId(_) => "invalid_transaction_id",
+            FaucetError::ClaimNotFound(_) => "claim_not_found",
+            FaucetError::RateLimitedIp { .. } => "rate_limited_ip",
+            FaucetError::RateLimitedAddress { .. } => "rate_limited_address",
+            FaucetError::InsufficientFunds(_) => "insufficient_funds",
+            FaucetError::QueueFull { .. } => "queue_full",
+            FaucetError::TransactionTooLarge(_) => "transaction_too_large",
+            FaucetError::AmountTooSmall(_) => "amount_too_small",
+            FaucetError::NodeUnavailable(_) => "node_unavailable",
+            FaucetError::NodeDisconnected { .. } => "node_disconnected",
+            FaucetError::NodeNotSynced => "node_not_synced",
+            FaucetError::SubmitRejected(_) => "submit_rejected",
+            FaucetError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            FaucetError::InvalidRequest(_)
+            | FaucetError::InvalidAddress(_)
+            | FaucetError::WrongNetwork { .. }
+            | FaucetError::InvalidTransactionId(_) => StatusCode::BAD_REQUEST,
+            FaucetError::ClaimNotFound(_) => StatusCode::NOT_FOUND,
+            FaucetError::RateLimitedIp { .. } | FaucetError::RateLimitedAddress { .. } => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            FaucetError::InsufficientFunds(_)
+            | FaucetError::QueueFull { .. }
+            | FaucetError::TransactionTooLarge(_)
+            | FaucetError::NodeUnavailable(_)
+            | FaucetError::NodeDisconnected { .. }
+            | FaucetError::NodeNotSynced => StatusCode::SERVICE_UNAVAILABLE,
+            FaucetError::SubmitRejected(_) => StatusCode::BAD_GATEWAY,
+            FaucetError::AmountTooSmall(_) | FaucetError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn retry_after_seconds(&self) -> Option<u64> {
+        match self {
+            FaucetError::RateLimitedIp { retry_after_seconds }
+            | FaucetError::RateLimitedAddress { retry_after_seconds }
+            | FaucetError::QueueFull { retry_after_seconds }
+            | FaucetError::NodeDisconnected { retry_after_seconds } => Some(*retry_after_seconds),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FaucetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaucetError::InvalidRequest(e) => write!(f, "Invalid request: {e}"),
+            FaucetError::InvalidAddress(e) => write!(f, "Invalid address: {e}"),
+            FaucetError::WrongNetwork { expected, found } => {
+                write!(f, "Address is for the wrong network: expected a {expected}: address, got {found}:")
+            }
+            FaucetError::InvalidTransactionId(id) => write!(f, "Invalid transaction id: {id}"),
+            FaucetError::ClaimNotFound(id) => write!(f, "Unknown claim transaction: {id}"),
+            FaucetError::RateLimitedIp { retry_after_seconds } => {
+                write!(f, "IP rate limit exceeded, try again in {retry_after_seconds} seconds")
+            }
+            FaucetError::RateLimitedAddress { retry_after_seconds } => {
+                write!(f, "Address rate limit exceeded, try again in {retry_after_seconds} seconds")
+            }
+            FaucetError::InsufficientFunds(e)
+            | FaucetError::TransactionTooLarge(e)
+            | FaucetError::AmountTooSmall(e) => write!(f, "{e}"),
+            FaucetError::QueueFull { retry_after_seconds } => {
+                write!(f, "Faucet is busy, try again in {retry_after_seconds} seconds")
+            }
+            FaucetError::NodeUnavailable(e) => write!(f, "Kaspa node unavailable: {e}"),
+            FaucetError::NodeDisconnected { retry_after_seconds } => {
+                write!(f, "Faucet is reconnecting to its Kaspa node, try again in {retry_after_seconds} seconds")
+            }
+            FaucetError::NodeNotSynced => write!(f, "Kaspa node is not synced yet, try again later"),
+            FaucetError::SubmitRejected(e) => write!(f, "Transaction rejected by node: {e}"),
+            FaucetError::Internal(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for FaucetError {}
+
+impl IntoResponse for FaucetError {
+    fn into_response(self) -> Response {
+        let body = Json(ErrorBody {
+            code: self.code(),
+            message: self.to_string(),
+            retry_after_seconds: self.retry_after_seconds(),
+        });
+        match self.retry_after_seconds() {
+            Some(seconds) => (self.status(), [(header::RETRY_AFTER, seconds.to_string())], body).into_response(),
+            None => (self.status(), body).into_response(),
+        }
+    }
+}
+
+impl From<BuildError> for FaucetError {
+    fn from(e: BuildError) -> Self {
+        match e {
+            BuildError::Insufficient { .. } => FaucetError::InsufficientFunds(e.to_string()),
+            BuildError::TooLarge {
+                kind: MassKind::Compute,
+                ..
+            } => FaucetError::TransactionTooLarge(e.to_string()),
+            BuildError::TooLarge {
+                kind: MassKind::Storage,
+                ..
+            } => FaucetError::AmountTooSmall(e.to_string()),
+        }
+    }
+}
+
+impl From<JsonRejection> for FaucetError {
+    fn from(e: JsonRejection) -> Self {
+        FaucetError::InvalidRequest(e.body_text())
+    }
+}
+
+impl From<TryClaimError> for FaucetError {
+    fn from(e: TryClaimError) -> Self {
+        match e {
+            TryClaimError::RateLimited {
+                limit: Limit::Ip,
+                retry_after_seconds,
+            } => FaucetError::RateLimitedIp { retry_after_seconds },
+            TryClaimError::RateLimited {
+                limit: Limit::Address,
+                retry_after_seconds,
+            } => FaucetError::RateLimitedAddress { retry_after_seconds },
+            TryClaimError::Ledger(e) => FaucetError::Internal(format!("Claim ledger unavailable: {e:#}")),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use kaspa_consensus_core::{
+    config::params::Params,
+    mass::MassCalculator,
+    network::NetworkId,
+    tx::{PopulatedTransaction, Transaction, UtxoEntry},
+};
+use kaspa_rpc_core::api::rpc::RpcApi;
+use serde::{Deserialize, Serialize};
+
+/// Largest transaction mass kaspad will relay as standard.
+pub const MAXIMUM_STANDARD_TRANSACTION_MASS: u64 = 100_000;
+
+/// Minimum relay feerate, in sompi per gram of mass.
+const MINIMUM_FEERATE: f64 = 1.0;
+
+/// Which bucket of `get_fee_estimate` to pay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeePriority {
+    Priority,
+    #[default]
+    Normal,
+    Low,
+}
+
+/// Prices transactions by their consensus mass at the node's current feerate.
+pub struct FeeCalculator {
+    masses: MassCalculator,
+    storage_mass_parameter: u64,
+    feerate: f64,
+}
+
+impl FeeCalculator {
+    /// Masses are computed with `network`'s consensus parameters.
+    pub fn new(network: NetworkId, feerate: f64) -> Self {
+        let params = Params::from(network);
+        Self {
+            masses: MassCalculator::new_with_consensus_params(&params),
+            storage_mass_parameter: params.storage_mass_parameter,
+            feerate: feerate.max(MINIMUM_FEERATE),
+        }
+    }
+
+    /// Fetches the current feerate for `priority` from the node.
+    pub async fn fetch(client: &dyn RpcApi, network: NetworkId, priority: FeePriority) -> anyhow::Result<Self> {
+        let estimate = client
+            .get_fee_estimate()
+            .await
+            .map_err(|e| anyhow::anyhow!("get_fee_estimate failed: {e}"))?;
+
+        let priority_bucket = estimate.priority_bucket;
+        let normal_bucket = estimate.normal_buckets.first().cloned();
+        let low_bucket = estimate.low_buckets.first().cloned();
+        let bucket = match priority {
+            FeePriority::Priority => priority_bucket,
+            FeePriority::Normal => normal_bucket.unwrap_or(priority_bucket),
+            FeePriority::Low => low_bucket.or(normal_bucket).unwrap_or(priority_bucket),
+        };
+        Ok(Self::new(network, bucket.feerate))
+    }
+
+    /// Compute mass alone, which doesn't depend on the spent entries.
+    pub fn compute_mass(&self, tx: &Transaction) -> u64 {
+        let non_contextual = self.masses.calc_non_contextual_masses(tx);
+        non_contextual.compute_mass.max(non_contextual.transient_mass)
+    }
+
+    /// Mass that the transaction will be charged for: the larger of its
+    /// compute (including transient) mass and its storage mass.
+    pub fn mass(&self, tx: &Transaction, entries: &[UtxoEntry]) -> u64 {
+        let populated = PopulatedTransaction::new(tx, entries.to_vec());
+        let storage_mass = self
+            .masses
+            .calc_contextual_masses(&populated)
+            .map(|m| m.storage_mass)
+            .unwrap_or(u64::MAX);
+        self.compute_mass(tx).max(storage_mass)
+    }
+
+    /// Storage mass an output of `value` sompi brings on its own.
+    pub fn output_storage_mass(&self, value: u64) -> u64 {
+        self.storage_mass_parameter / value.max(1)
+    }
+
+    pub fn fee(&self, mass: u64) -> u64 {
+        (mass as f64 * self.feerate).ceil() as u64
+    }
+}
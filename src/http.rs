@@ -0,0 +1,163 @@
+use axum::{
+    extract::{rejection::JsonRejection, ConnectInfo, Path, State},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Json,
+    },
+    routing::{get, post},
+    Router,
+};
+use futures::{stream, Stream, StreamExt};
+use kaspa_addresses::Address;
+use kaspa_rpc_core::RpcTransactionId;
+use serde::{Deserialize, Serialize};
+use std::{net::SocketAddr, str::FromStr};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{error, info, warn};
+
+use crate::error::FaucetError;
+use crate::tracker::ClaimStatus;
+use crate::{format_kas_from_sompi, ledger, Faucet, Status};
+
+#[derive(Deserialize)]
+struct ClaimRequest {
+    address: String,
+}
+
+#[derive(Serialize)]
+struct ClaimResponse {
+    transaction_id: String,
+    amount_kas: String,
+    next_claim_seconds: u64,
+}
+
+/// Routes of one faucet: `/status`, `/claim` and the claim tracking endpoints.
+/// Claims need the peer address, so serve with
+/// `into_make_service_with_connect_info::<SocketAddr>()`.
+pub fn router(faucet: Faucet) -> Router {
+    Router::new()
+        .route("/status", get(status_handler))
+        .route("/claim", post(claim_handler))
+        .route("/claim/{txid}", get(claim_status_handler))
+        .route("/claim/{txid}/events", get(claim_events_handler))
+        .with_state(faucet)
+}
+
+async fn status_handler(State(faucet): State<Faucet>) -> Json<Status> {
+    Json(faucet.status())
+}
+
+async fn claim_handler(
+    State(faucet): State<Faucet>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    payload: Result<Json<ClaimRequest>, JsonRejection>,
+) -> Result<Json<ClaimResponse>, FaucetError> {
+    let ip = addr.ip().to_string();
+    let Json(payload) = payload.map_err(|e| {
+        warn!("Malformed claim request from IP: {ip}: {e}");
+        FaucetError::from(e)
+    })?;
+    info!("Claim request from IP: {}, address: {}", ip, payload.address);
+
+    faucet.ensure_ready()?;
+
+    let destination: Address = payload.address.as_str().try_into().map_err(|e| {
+        warn!("Invalid address: {}", e);
+        FaucetError::InvalidAddress(e.to_string())
+    })?;
+    faucet.ensure_network(&destination)?;
+
+    // The claim runs in its own task: if the client hangs up while it waits
+    // for a batch, the payment still goes out and must still be recorded
+    tokio::spawn(pay_claim(faucet, ip, destination))
+        .await
+        .map_err(|e| FaucetError::Internal(format!("Claim task failed: {e}")))?
+}
+
+/// Pays a claim that passes both rate limits and records it in the ledger.
+async fn pay_claim(faucet: Faucet, ip: String, destination: Address) -> Result<Json<ClaimResponse>, FaucetError> {
+    // Rate limit check: both the IP and the destination address must be allowed
+    let permit = faucet
+        .rate_limiter
+        .try_claim(&ip, &destination.to_string())
+        .map_err(|e| {
+            let e = FaucetError::from(e);
+            warn!("Claim from IP: {ip}, address: {destination} refused: {e}");
+            e
+        })?;
+
+    let tx_id = faucet.send(&destination, faucet.amount_per_claim).await?;
+
+    let record = ledger::ClaimRecord {
+        ip,
+        address: destination.to_string(),
+        amount_sompi: faucet.amount_per_claim,
+        transaction_id: tx_id.to_string(),
+        claimed_at: ledger::unix_now(),
+    };
+    if let Err(e) = permit.record(&record) {
+        // The transaction is already out; don't fail the response over bookkeeping.
+        error!("Failed to record claim {} in ledger: {e:?}", record.transaction_id);
+    }
+
+    Ok(Json(ClaimResponse {
+        transaction_id: tx_id.to_string(),
+        amount_kas: format_kas_from_sompi(faucet.amount_per_claim),
+        next_claim_seconds: faucet.claim_interval_seconds,
+    }))
+}
+
+async fn claim_status_handler(
+    State(faucet): State<Faucet>,
+    Path(txid): Path<String>,
+) -> Result<Json<ClaimStatus>, FaucetError> {
+    let tx_id = RpcTransactionId::from_str(&txid).map_err(|_| FaucetError::InvalidTransactionId(txid.clone()))?;
+    faucet
+        .tracker
+        .status(&tx_id)
+        .map(Json)
+        .ok_or(FaucetError::ClaimNotFound(txid))
+}
+
+async fn claim_events_handler(
+    State(faucet): State<Faucet>,
+    Path(txid): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, FaucetError> {
+    let tx_id = RpcTransactionId::from_str(&txid).map_err(|_| FaucetError::InvalidTransactionId(txid.clone()))?;
+    let tracker = faucet.tracker.clone();
+    let (history, receiver) = tracker.subscribe(&tx_id).ok_or(FaucetError::ClaimNotFound(txid))?;
+
+    // Replay what already happened, then follow live events until a terminal one
+    let seen = history.len();
+    let finished = history.last().is_some_and(|e| e.is_terminal());
+    let live = stream::unfold((receiver, seen, finished), move |(mut receiver, seen, finished)| {
+        let tracker = tracker.clone();
+        async move {
+            if finished {
+                return None;
+            }
+            loop {
+                match receiver.recv().await {
+                    Ok((id, event)) if id == tx_id => {
+                        let finished = event.is_terminal();
+                        return Some((vec![event], (receiver, seen + 1, finished)));
+                    }
+                    Ok(_) => continue,
+                    // The channel is shared by every claim, so ours may be among
+                    // the missed events: catch up from the claim's own history
+                    Err(RecvError::Lagged(_)) => {
+                        let (history, receiver) = tracker.subscribe(&tx_id)?;
+                        let finished = history.last().is_some_and(|e| e.is_terminal());
+                        let missed = history[seen.min(history.len())..].to_vec();
+                        return Some((missed, (receiver, history.len(), finished)));
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
+    let events = stream::iter(history)
+        .chain(live.flat_map(stream::iter))
+        .map(|event| Event::default().event(event.name()).json_data(&event));
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
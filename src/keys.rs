@@ -0,0 +1,59 @@
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_consensus_core::network::NetworkId;
+use secp256k1::SecretKey;
+use std::io::Write;
+use std::str::FromStr;
+use tracing::warn;
+
+/// A fresh random faucet key.
+pub fn generate() -> SecretKey {
+    SecretKey::new(&mut secp256k1::rand::thread_rng())
+}
+
+/// Parses a hex `faucet_private_key`.
+pub fn parse(hex: &str) -> anyhow::Result<SecretKey> {
+    SecretKey::from_str(hex.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid faucet_private_key (expected 32-byte hex): {e}"))
+}
+
+/// The P2PK address `key` pays from on `network`, e.g. `kaspatest:...` on a testnet.
+pub fn address(key: &SecretKey, network: NetworkId) -> Address {
+    let public_key = secp256k1::PublicKey::from_secret_key_global(key);
+    let (x_only_public_key, _) = public_key.x_only_public_key();
+    Address::new(Prefix::from(network), Version::PubKey, &x_only_public_key.serialize())
+}
+
+pub fn to_hex(key: &SecretKey) -> String {
+    key.secret_bytes().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Reads a key written by `faucet keygen --out` or `faucet init --key-file`.
+pub fn read_key_file(path: &str) -> anyhow::Result<String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read key file {path}: {e}"))?
+            .permissions()
+            .mode();
+        if mode & 0o077 != 0 {
+            warn!("Key file {path} is readable by other users (mode {:o}); chmod 600 it", mode & 0o777);
+        }
+    }
+    let key = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Failed to read key file {path}: {e}"))?;
+    Ok(key.trim().to_string())
+}
+
+/// Creates `path` readable by its owner only. Never overwrites: losing a
+/// funded key loses its funds.
+pub fn write_secret(path: &str, contents: &str) -> anyhow::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to create {path}: {e}"))?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
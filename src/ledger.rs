@@ -0,0 +1,90 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A single successful faucet payout.
+#[derive(Debug, Clone)]
+pub struct ClaimRecord {
+    pub ip: String,
+    pub address: String,
+    pub amount_sompi: u64,
+    pub transaction_id: String,
+    pub claimed_at: u64,
+}
+
+/// Durable claim history stored in an embedded SQLite file.
+pub struct Ledger {
+    conn: Mutex<Connection>,
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl Ledger {
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS claims (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                ip TEXT NOT NULL,
+                address TEXT NOT NULL,
+                amount_sompi INTEGER NOT NULL,
+                transaction_id TEXT NOT NULL,
+                claimed_at INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS claims_by_ip ON claims (ip, claimed_at);
+            CREATE INDEX IF NOT EXISTS claims_by_address ON claims (address, claimed_at);",
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn record_claim(&self, claim: &ClaimRecord) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO claims (ip, address, amount_sompi, transaction_id, claimed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                claim.ip,
+                claim.address,
+                claim.amount_sompi as i64,
+                claim.transaction_id,
+                claim.claimed_at as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Unix timestamp of the most recent claim made from `ip`, if any.
+    pub fn last_claim_by_ip(&self, ip: &str) -> anyhow::Result<Option<u64>> {
+        let conn = self.conn.lock().unwrap();
+        let last: Option<i64> = conn
+            .query_row(
+                "SELECT MAX(claimed_at) FROM claims WHERE ip = ?1",
+                params![ip],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(last.map(|t| t as u64))
+    }
+
+    /// Unix timestamp of the most recent claim paid to `address`, if any.
+    pub fn last_claim_by_address(&self, address: &str) -> anyhow::Result<Option<u64>> {
+        let conn = self.conn.lock().unwrap();
+        let last: Option<i64> = conn
+            .query_row(
+                "SELECT MAX(claimed_at) FROM claims WHERE address = ?1",
+                params![address],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(last.map(|t| t as u64))
+    }
+}
@@ -0,0 +1,381 @@
+//! A Kaspa faucet. `Faucet` owns the node connection, wallet and claim
+//! queue; `http::router` serves it over HTTP, but it can just as well be
+//! driven directly.
+
+use kaspa_addresses::Address;
+use kaspa_consensus_core::{
+    network::NetworkId,
+    sign::sign_with_multiple_v2,
+    tx::{SignableTransaction, TransactionOutpoint, TransactionOutput, UtxoEntry},
+};
+use kaspa_rpc_core::{api::rpc::RpcApi, RpcTransaction, RpcTransactionId};
+use kaspa_txscript::standard::pay_to_address_script;
+use serde::Serialize;
+use std::{
+    cmp::Reverse,
+    sync::{Arc, Mutex},
+};
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use tracing::{info, warn};
+
+mod batch;
+mod builder;
+pub mod coin_selection;
+pub mod config;
+mod consolidate;
+pub mod error;
+pub mod fees;
+pub mod http;
+pub mod keys;
+mod ledger;
+#[cfg(test)]
+mod mock;
+mod nodes;
+mod notify;
+mod rate_limiter;
+pub mod rpc;
+#[cfg(test)]
+mod tests;
+mod tracker;
+mod utxo;
+
+use batch::ClaimQueue;
+use builder::{BuildError, UnsignedTransaction};
+use coin_selection::CoinSelector;
+use config::{ConsolidationConfig, FaucetConfig};
+use error::FaucetError;
+use fees::{FeeCalculator, FeePriority};
+use nodes::NodePool;
+use rpc::{DynNodeRpc, Endpoint, NodeRpc, Transport};
+use tracker::ClaimTracker;
+use utxo::{Reservation, UtxoManager, UNACCEPTED_DAA_SCORE};
+
+pub use utxo::{Balances, Utxo};
+
+pub fn format_kas_from_sompi(amount_sompi: u64) -> String {
+    const SOMPI_PER_KAS: u64 = 100_000_000;
+    let whole = amount_sompi / SOMPI_PER_KAS;
+    let frac = amount_sompi % SOMPI_PER_KAS;
+    format!("{}.{:08}", whole, frac)
+}
+
+/// Snapshot of a faucet's node, wallet and claim policy, as served by `/status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    pub active: bool,
+    pub network: String,
+    /// kaspad endpoint currently serving the faucet.
+    pub node: String,
+    pub is_synced: bool,
+    pub virtual_daa_score: u64,
+    pub peers: usize,
+    pub faucet_address: String,
+    pub balance_kas: String,
+    pub spendable_balance_kas: String,
+    pub immature_balance_kas: String,
+    pub next_claim_seconds: u64,
+}
+
+/// A running faucet: its node connections, wallet and background workers.
+/// Cheap to clone; clones share everything.
+#[derive(Clone)]
+pub struct Faucet {
+    nodes: Arc<NodePool>,
+    network: NetworkId,
+    faucet_address: Address,
+    faucet_private_key: [u8; 32],
+    amount_per_claim: u64,
+    claim_interval_seconds: u64,
+    fee_priority: FeePriority,
+    coin_selector: Arc<dyn CoinSelector>,
+    rate_limiter: Arc<rate_limiter::RateLimiter>,
+    utxo_manager: Arc<UtxoManager>,
+    claim_queue: ClaimQueue,
+    tracker: Arc<ClaimTracker>,
+    /// Background workers, stopped by `shutdown`.
+    tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl Faucet {
+    /// Connects to the faucet's node and starts its background workers. `rpc`,
+    /// if given, is an already connected node used instead of `kaspad_urls`.
+    pub async fn new(config: &FaucetConfig, rpc: Option<DynNodeRpc>) -> anyhow::Result<Self> {
+        let faucet_private_key = keys::parse(&config.faucet_private_key)?;
+        let faucet_private_key_bytes = faucet_private_key.secret_bytes();
+
+        // The faucet address, and every claim address, must carry the network's prefix
+        let faucet_address = keys::address(&faucet_private_key, config.network);
+        info!("Faucet address on {}: {}", config.network, faucet_address);
+
+        // Claim ledger and the rate limiter built on top of it
+        let ledger = Arc::new(ledger::Ledger::open(&config.ledger_path)?);
+        info!("Using claim ledger at: {}", config.ledger_path);
+        let rate_limiter = Arc::new(rate_limiter::RateLimiter::new(
+            ledger,
+            Duration::from_secs(config.claim_interval_seconds),
+            Duration::from_secs(config.address_claim_interval_seconds),
+        ));
+
+        // Connect to kaspad. Notifications keep the faucet's UTXO set live and
+        // follow the active node on failover.
+        let utxo_manager = Arc::new(UtxoManager::new(config.coinbase_maturity()));
+        let tracker = Arc::new(ClaimTracker::new(config.confirmation_depth));
+        let subscriber = notify::Subscriber::new(faucet_address.clone(), utxo_manager.clone(), tracker.clone());
+        let nodes = match rpc {
+            Some(rpc) => {
+                // Without a URL to reconnect to, a lost in-process node stays lost
+                let endpoint = config.endpoints().into_iter().next().unwrap_or_else(|| Endpoint {
+                    url: "in-process".to_string(),
+                    transport: Transport::Grpc,
+                });
+                NodePool::with_client(endpoint, rpc, config.network, subscriber).await?
+            }
+            None => NodePool::start(config.endpoints(), config.network, subscriber).await?,
+        };
+        let mut tasks = vec![
+            nodes::spawn_supervisor(nodes.clone()),
+            nodes::spawn_health_checks(nodes.clone(), Duration::from_secs(config.health_check_interval_seconds)),
+            tracker::spawn_mempool_check(nodes.clone(), tracker.clone()),
+        ];
+
+        // Claims are paid in batches from a single transaction
+        let (claim_queue, claim_receiver) = ClaimQueue::new(Duration::from_millis(config.batch_window_ms));
+
+        let faucet = Self {
+            nodes,
+            network: config.network,
+            faucet_address,
+            faucet_private_key: faucet_private_key_bytes,
+            amount_per_claim: config.amount_per_claim,
+            claim_interval_seconds: config.claim_interval_seconds,
+            fee_priority: config.fee_priority,
+            coin_selector: config.coin_selection.selector(),
+            rate_limiter,
+            utxo_manager,
+            claim_queue,
+            tracker,
+            tasks: Arc::new(Mutex::new(Vec::new())),
+        };
+        tasks.push(batch::spawn_worker(
+            faucet.clone(),
+            claim_receiver,
+            Duration::from_millis(config.batch_window_ms),
+            config.batch_max_claims.max(1),
+        ));
+        if config.consolidation.enabled {
+            tasks.push(consolidate::spawn(faucet.clone(), config.consolidation.clone()));
+        }
+        *faucet.tasks.lock().unwrap() = tasks;
+
+        Ok(faucet)
+    }
+
+    pub fn network(&self) -> NetworkId {
+        self.network
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.faucet_address
+    }
+
+    /// The faucet's UTXO balance as last reported by the node, plus change
+    /// from its own pending transactions.
+    pub fn balance(&self) -> Balances {
+        self.utxo_manager.balances()
+    }
+
+    /// Pays `amount` sompi to `to`, batched with any pending claims. Rate
+    /// limits are not applied; they belong to whoever takes the claim.
+    pub async fn send(&self, to: &Address, amount: u64) -> Result<RpcTransactionId, FaucetError> {
+        // A zero-value output is invalid and has no defined storage mass
+        if amount == 0 {
+            return Err(FaucetError::AmountTooSmall("Amount must be more than 0 sompi".to_string()));
+        }
+        self.ensure_ready()?;
+        self.ensure_network(to)?;
+        self.claim_queue.submit(to.clone(), amount).await
+    }
+
+    /// Sends every spendable UTXO that fits in one transaction to `to`, minus
+    /// the fee. Run it again to sweep what didn't fit.
+    pub async fn sweep(&self, to: &Address) -> Result<RpcTransactionId, FaucetError> {
+        self.ensure_ready()?;
+        self.ensure_network(to)?;
+        let client = self.nodes.client();
+        let fees = FeeCalculator::fetch(client.as_ref(), self.network, self.fee_priority)
+            .await
+            .map_err(|e| FaucetError::NodeUnavailable(format!("{e:#}")))?;
+        let sweep_script = pay_to_address_script(to);
+
+        // Largest first, so whatever the mass limit leaves behind is the small change
+        let reservation = self.utxo_manager.reserve(|mut spendable| -> Result<_, FaucetError> {
+            spendable.sort_by_key(|u| Reverse(u.entry.amount));
+            let mut selected = Vec::new();
+            for utxo in spendable {
+                selected.push(utxo);
+                if let Err(BuildError::TooLarge { .. }) = builder::build_transaction(&selected, &[], &sweep_script, &fees) {
+                    selected.pop();
+                    break;
+                }
+            }
+            Ok(selected)
+        })?;
+
+        let mut unsigned = builder::build_transaction(reservation.utxos(), &[], &sweep_script, &fees)?;
+        if unsigned.tx.outputs.is_empty() {
+            return Err(FaucetError::InsufficientFunds(format!(
+                "Faucet has nothing left to sweep after a fee of {} sompi",
+                unsigned.fee
+            )));
+        }
+        // The builder's change output is the sweep itself, which the faucet doesn't own
+        unsigned.change = None;
+        sign_and_submit(client.as_ref(), reservation, unsigned, &self.faucet_private_key, |_| {}).await
+    }
+
+    /// Runs one consolidation round now. Returns `None` when there are fewer
+    /// than `config.min_utxos` UTXOs below the threshold.
+    pub async fn consolidate(&self, config: &ConsolidationConfig) -> anyhow::Result<Option<RpcTransactionId>> {
+        consolidate::consolidate(self, config).await
+    }
+
+    /// Every UTXO the faucet owns, with whether it is spendable yet
+    /// (immature coinbase outputs aren't).
+    pub fn utxos(&self) -> Vec<(Utxo, bool)> {
+        self.utxo_manager.utxos()
+    }
+
+    pub fn status(&self) -> Status {
+        let balances = self.balance();
+        let sync_state = self.nodes.sync_state();
+        Status {
+            active: self.nodes.degraded().is_none() && sync_state.is_synced,
+            network: self.network.to_string(),
+            node: self.nodes.active_url().to_string(),
+            is_synced: sync_state.is_synced,
+            virtual_daa_score: sync_state.virtual_daa_score,
+            peers: sync_state.peers,
+            faucet_address: self.faucet_address.to_string(),
+            balance_kas: format_kas_from_sompi(balances.spendable + balances.immature),
+            spendable_balance_kas: format_kas_from_sompi(balances.spendable),
+            immature_balance_kas: format_kas_from_sompi(balances.immature),
+            next_claim_seconds: self.claim_interval_seconds,
+        }
+    }
+
+    /// Stops the background workers and disconnects from kaspad; claims still
+    /// queued fail. The workers hold clones of the faucet, so dropping it
+    /// doesn't stop them: anything that outlives a faucet must call this.
+    pub async fn shutdown(&self) {
+        for task in self.tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+        self.nodes.disconnect().await;
+    }
+
+    /// Fails while the active node is disconnected or not synced.
+    fn ensure_ready(&self) -> Result<(), FaucetError> {
+        if let Some(retry_after_seconds) = self.nodes.degraded() {
+            return Err(FaucetError::NodeDisconnected { retry_after_seconds });
+        }
+        if !self.nodes.sync_state().is_synced {
+            return Err(FaucetError::NodeNotSynced);
+        }
+        Ok(())
+    }
+
+    fn ensure_network(&self, destination: &Address) -> Result<(), FaucetError> {
+        if destination.prefix != self.faucet_address.prefix {
+            warn!("Address {} is not on the faucet's network", destination);
+            return Err(FaucetError::WrongNetwork {
+                expected: self.faucet_address.prefix,
+                found: destination.prefix,
+            });
+        }
+        Ok(())
+    }
+}
+
+async fn submit_faucet_transaction(
+    faucet: &Faucet,
+    payments: &[(Address, u64)],
+    on_signed: impl FnOnce(RpcTransactionId),
+) -> Result<RpcTransactionId, FaucetError> {
+    let client = faucet.nodes.client();
+    let fees = FeeCalculator::fetch(client.as_ref(), faucet.network, faucet.fee_priority)
+        .await
+        .map_err(|e| FaucetError::NodeUnavailable(format!("{e:#}")))?;
+    let change_script = pay_to_address_script(&faucet.faucet_address);
+    let payments = payments
+        .iter()
+        .map(|(destination, amount)| TransactionOutput::new(*amount, pay_to_address_script(destination)))
+        .collect::<Vec<_>>();
+
+    // Select under the manager's lock so a concurrent claim can't pick the same outpoints
+    // Immature coinbase outputs are never offered
+    let reservation = faucet.utxo_manager.reserve(|spendable| {
+        if spendable.is_empty() {
+            return Err(FaucetError::InsufficientFunds(format!(
+                "Faucet has no spendable UTXOs. Fund address {} first.",
+                faucet.faucet_address
+            )));
+        }
+        Ok(faucet
+            .coin_selector
+            .select(spendable, &payments, &change_script, &fees)?)
+    })?;
+
+    let unsigned = builder::build_transaction(reservation.utxos(), &payments, &change_script, &fees)?;
+    sign_and_submit(
+        client.as_ref(),
+        reservation,
+        unsigned,
+        &faucet.faucet_private_key,
+        on_signed,
+    )
+    .await
+}
+
+/// Signs `unsigned` and submits it, calling `on_signed` with the id in
+/// between. The reservation is committed when the node accepts the
+/// transaction and released if anything fails.
+async fn sign_and_submit(
+    client: &dyn NodeRpc,
+    reservation: Reservation<'_>,
+    unsigned: UnsignedTransaction,
+    private_key: &[u8; 32],
+    on_signed: impl FnOnce(RpcTransactionId),
+) -> Result<RpcTransactionId, FaucetError> {
+    info!(
+        "Built transaction with {} inputs, {} outputs, fee {} sompi",
+        unsigned.tx.inputs.len(),
+        unsigned.tx.outputs.len(),
+        unsigned.fee
+    );
+    let signable_tx = SignableTransaction::with_entries(unsigned.tx, unsigned.entries);
+    let signed_tx = sign_with_multiple_v2(signable_tx, std::slice::from_ref(private_key))
+        .fully_signed()
+        .map_err(|e| FaucetError::Internal(format!("Failed to sign transaction: {e}")))?;
+
+    on_signed(signed_tx.tx.id());
+
+    let rpc_transaction: RpcTransaction = signed_tx.tx.as_ref().into();
+    let tx_id = client
+        .submit_transaction(rpc_transaction, false)
+        .await
+        .map_err(|e| {
+            if client.is_connected() {
+                FaucetError::SubmitRejected(e.to_string())
+            } else {
+                FaucetError::NodeUnavailable(e.to_string())
+            }
+        })?;
+
+    let change = unsigned.change.map(|(index, output)| Utxo {
+        outpoint: TransactionOutpoint::new(tx_id, index),
+        entry: UtxoEntry::new(output.value, output.script_public_key, UNACCEPTED_DAA_SCORE, false),
+    });
+    reservation.commit(change);
+    Ok(tx_id)
+}
@@ -1,152 +1,153 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
     response::{Html, Json},
-    routing::{get, post},
+    routing::get,
     Router,
 };
-use kaspa_addresses::{Address, Prefix, Version};
-use kaspa_consensus_core::{
-    sign::sign_with_multiple_v2,
-    subnets::SUBNETWORK_ID_NATIVE,
-    tx::{SignableTransaction, Transaction, TransactionInput, TransactionOutput, UtxoEntry},
+use clap::{Parser, Subcommand};
+use faucet::{
+    config::{parse_kas_to_sompi, Config, FaucetConfig, CONFIG_PATH},
+    format_kas_from_sompi, http, keys, Faucet,
 };
-use kaspa_grpc_client::GrpcClient;
-use kaspa_rpc_core::{api::rpc::RpcApi, notify::mode::NotificationMode, RpcTransaction};
-use kaspa_txscript::standard::pay_to_address_script;
-use serde::{Deserialize, Serialize};
-use std::{net::SocketAddr, str::FromStr, sync::Arc};
+use kaspa_addresses::Address;
+use kaspa_consensus_core::network::NetworkId;
+use serde::Serialize;
+use std::net::SocketAddr;
 use tokio::net::TcpListener;
-use tokio::time::Duration;
 use tower_http::cors::CorsLayer;
 use tower_http::services::ServeDir;
-use tracing::{error, info, warn};
-
-mod config;
-mod rate_limiter;
-
-use config::Config;
+use tracing::info;
 
 const INDEX_HTML: &str = include_str!("../static/index.html");
 
-fn format_kas_from_sompi(amount_sompi: u64) -> String {
-    const SOMPI_PER_KAS: u64 = 100_000_000;
-    let whole = amount_sompi / SOMPI_PER_KAS;
-    let frac = amount_sompi % SOMPI_PER_KAS;
-    format!("{}.{:08}", whole, frac)
-}
-
-#[derive(Serialize)]
-struct StatusResponse {
-    active: bool,
-    faucet_address: String,
-    balance_kas: String,
-    next_claim_seconds: u64,
-}
-
-#[derive(Deserialize)]
-struct ClaimRequest {
-    address: String,
+#[derive(Parser)]
+#[command(about = "Kaspa test network faucet")]
+struct Cli {
+    /// Faucet to act on, by its `path`, when the config has several.
+    #[arg(long, global = true)]
+    faucet: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-#[derive(Serialize)]
-struct ClaimResponse {
-    transaction_id: String,
-    amount_kas: String,
-    next_claim_seconds: u64,
+#[derive(Subcommand)]
+enum Command {
+    /// Serve every configured faucet over HTTP (the default).
+    Serve,
+    /// Create faucet-config.toml with a newly generated faucet key.
+    Init {
+        #[arg(long, default_value = "testnet-12")]
+        network: NetworkId,
+        /// Keep the key in this file instead of in the config.
+        #[arg(long)]
+        key_file: Option<String>,
+    },
+    /// Generate a new faucet private key and print it with its address.
+    Keygen {
+        #[arg(long, default_value = "testnet-12")]
+        network: NetworkId,
+        /// Write the key to this file (created with mode 0600) instead of printing it.
+        #[arg(long)]
+        out: Option<String>,
+    },
+    /// Print the faucet address.
+    Address,
+    /// Print the faucet balance.
+    Balance,
+    /// Send KAS from the faucet to an address.
+    Send {
+        address: String,
+        /// Amount in KAS, e.g. 1.5
+        #[arg(value_parser = parse_kas_to_sompi)]
+        amount: u64,
+    },
+    /// Send everything spendable that fits in one transaction to an address.
+    Sweep { address: String },
+    /// Merge the faucet's small UTXOs now.
+    Consolidate,
+    /// List the faucet's UTXOs.
+    Utxos,
 }
 
-#[derive(Clone)]
-struct AppState {
-    client: GrpcClient,
-    faucet_address: Address,
-    faucet_private_key: [u8; 32],
-    amount_per_claim: u64,
-    claim_interval_seconds: u64,
-    rate_limiter: Arc<rate_limiter::RateLimiter>,
+/// Entry in `GET /faucets`, used by the index page to list every faucet.
+#[derive(Clone, Serialize)]
+struct FaucetInfo {
+    path: String,
+    network: String,
+    faucet_address: String,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
-
-    let config = Config::load()?;
-    info!("Loaded config: {:?}", config);
-    let port = config.port;
-
-    let faucet_private_key = secp256k1::SecretKey::from_str(&config.faucet_private_key)
-        .map_err(|e| anyhow::anyhow!("Invalid faucet_private_key (expected 32-byte hex): {e}"))?;
-    let faucet_private_key_bytes = faucet_private_key.secret_bytes();
-
-    let public_key = secp256k1::PublicKey::from_secret_key_global(&faucet_private_key);
-    let (x_only_public_key, _) = public_key.x_only_public_key();
-    let faucet_address = Address::new(Prefix::Testnet, Version::PubKey, &x_only_public_key.serialize());
-
-    // Connect to kaspad
-    let grpc_url = if config.kaspad_url.starts_with("grpc://") {
-        config.kaspad_url.clone()
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Command::Serve);
+    // Admin commands print their results on stdout, so logs go to stderr
+    if matches!(command, Command::Serve) {
+        tracing_subscriber::fmt::init();
     } else {
-        format!(
-            "grpc://{}",
-            config
-                .kaspad_url
-                .replace("http://", "")
-                .replace("https://", "")
-        )
-    };
-    info!("Connecting to kaspad at: {}", grpc_url);
+        tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+    }
 
-    let client = match GrpcClient::connect_with_args(
-        NotificationMode::Direct,
-        grpc_url.clone(),
-        None,
-        true,
-        None,
-        false,
-        Some(500_000),
-        Default::default(),
-    )
-    .await
-    {
-        Ok(c) => {
-            c.start(None).await;
-            c
+    match command {
+        Command::Serve => serve(Config::load()?).await,
+        Command::Init { network, key_file } => {
+            let address = Config::init(CONFIG_PATH, network, key_file.as_deref())?;
+            println!("Created {CONFIG_PATH}; check the settings, then fund the faucet address:");
+            println!("{address}");
+            Ok(())
         }
-        Err(e) => {
-            warn!("connect_with_args failed, falling back to connect(): {:?}", e);
-            let c = GrpcClient::connect(grpc_url).await?;
-            c.start(None).await;
-            c
+        Command::Keygen { network, out } => {
+            let key = keys::generate();
+            match out {
+                Some(path) => {
+                    keys::write_secret(&path, &keys::to_hex(&key))?;
+                    println!("Key file:    {path} (set faucet_private_key_file to use it)");
+                }
+                None => println!("Private key: {}", keys::to_hex(&key)),
+            }
+            println!("Address:     {}", keys::address(&key, network));
+            Ok(())
         }
-    };
-
-    let info = client.get_info().await?;
-    info!("Connected to kaspad: {:?}", info);
+        Command::Address => {
+            let config = select_faucet(Config::load()?, cli.faucet)?;
+            let key = keys::parse(&config.faucet_private_key)?;
+            println!("{}", keys::address(&key, config.network));
+            Ok(())
+        }
+        command => {
+            let config = select_faucet(Config::load()?, cli.faucet)?;
+            run(command, &config).await
+        }
+    }
+}
 
-    // Simple in-memory rate limiter
-    let rate_limiter = Arc::new(rate_limiter::RateLimiter::new(Duration::from_secs(
-        config.claim_interval_seconds,
-    )));
+async fn serve(config: Config) -> anyhow::Result<()> {
+    info!("Loaded config: {:?}", config);
 
-    let state = AppState {
-        client,
-        faucet_address,
-        faucet_private_key: faucet_private_key_bytes,
-        amount_per_claim: config.amount_per_claim,
-        claim_interval_seconds: config.claim_interval_seconds,
-        rate_limiter,
-    };
+    // Each faucet gets its own node connection, state and routes under its path
+    let mut app = Router::new();
+    let mut faucets = Vec::new();
+    for faucet_config in &config.faucets {
+        let faucet = Faucet::new(faucet_config, None).await?;
+        faucets.push(FaucetInfo {
+            path: faucet_config.path.clone(),
+            network: faucet.network().to_string(),
+            faucet_address: faucet.address().to_string(),
+        });
+        let routes = http::router(faucet);
+        app = if faucet_config.path.is_empty() {
+            app.merge(routes)
+        } else {
+            app.nest(&format!("/{}", faucet_config.path), routes)
+        };
+    }
 
-    // Build router
-    let app = Router::new()
+    let app = app
         .route("/", get(|| async { Html(INDEX_HTML) }))
+        .route("/faucets", get(move || async move { Json(faucets) }))
         .nest_service("/static", ServeDir::new("static"))
-        .route("/status", get(status_handler))
-        .route("/claim", post(claim_handler))
-        .layer(CorsLayer::permissive())
-        .with_state(state);
+        .layer(CorsLayer::permissive());
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
     info!("Faucet listening on http://{}", addr);
 
     let listener = TcpListener::bind(addr).await?;
@@ -159,132 +160,88 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn status_handler(
-    State(state): State<AppState>,
-) -> Result<Json<StatusResponse>, StatusCode> {
-    let balance = state
-        .client
-        .get_balance_by_address(state.faucet_address.clone())
-        .await
-        .map_err(|e| {
-            error!("Failed to get balance: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    Ok(Json(StatusResponse {
-        active: true,
-        faucet_address: state.faucet_address.to_string(),
-        balance_kas: format_kas_from_sompi(balance),
-        next_claim_seconds: state.claim_interval_seconds,
-    }))
-}
-
-async fn claim_handler(
-    State(state): State<AppState>,
-    axum::extract::ConnectInfo(addr): axum::extract::ConnectInfo<SocketAddr>,
-    Json(payload): Json<ClaimRequest>,
-) -> Result<Json<ClaimResponse>, StatusCode> {
-    let ip = addr.ip().to_string();
-    info!("Claim request from IP: {}, address: {}", ip, payload.address);
-
-    let destination: Address = payload.address.as_str().try_into().map_err(|e| {
-        warn!("Invalid address: {}", e);
-        StatusCode::BAD_REQUEST
-    })?;
-
-    // Rate limit check
-    if !state.rate_limiter.try_claim(&ip) {
-        warn!("Rate limit exceeded for IP: {}", ip);
-        return Err(StatusCode::TOO_MANY_REQUESTS);
+/// Runs an admin command that needs the faucet's node.
+async fn run(command: Command, config: &FaucetConfig) -> anyhow::Result<()> {
+    // A one-off command shouldn't kick off a background consolidation round
+    let mut config = config.clone();
+    config.consolidation.enabled = false;
+    let faucet = Faucet::new(&config, None).await?;
+
+    match command {
+        Command::Balance => {
+            let balance = faucet.balance();
+            println!("Address:   {}", faucet.address());
+            println!("Spendable: {} KAS", format_kas_from_sompi(balance.spendable));
+            println!("Immature:  {} KAS", format_kas_from_sompi(balance.immature));
+        }
+        Command::Send { address, amount } => {
+            let tx_id = faucet.send(&parse_address(&address)?, amount).await?;
+            println!("Sent {} KAS to {address} in transaction {tx_id}", format_kas_from_sompi(amount));
+        }
+        Command::Sweep { address } => {
+            let tx_id = faucet.sweep(&parse_address(&address)?).await?;
+            println!("Swept to {address} in transaction {tx_id}");
+            let left = faucet.balance();
+            if left.spendable + left.immature > 0 {
+                println!(
+                    "{} KAS is left; run sweep again once it is spendable",
+                    format_kas_from_sompi(left.spendable + left.immature)
+                );
+            }
+        }
+        Command::Consolidate => {
+            // Asked for explicitly, so merge even a couple of small UTXOs
+            let mut consolidation = config.consolidation.clone();
+            consolidation.min_utxos = 2;
+            match faucet.consolidate(&consolidation).await? {
+                Some(tx_id) => println!("Submitted consolidation transaction {tx_id}"),
+                None => println!(
+                    "Nothing to consolidate: fewer than two UTXOs below {} KAS",
+                    format_kas_from_sompi(consolidation.utxo_threshold)
+                ),
+            }
+        }
+        Command::Utxos => {
+            let mut utxos = faucet.utxos();
+            utxos.sort_by_key(|(utxo, _)| std::cmp::Reverse(utxo.entry.amount));
+            for (utxo, spendable) in &utxos {
+                println!(
+                    "{}:{}  {:>20} KAS  daa {}{}{}",
+                    utxo.outpoint.transaction_id,
+                    utxo.outpoint.index,
+                    format_kas_from_sompi(utxo.entry.amount),
+                    utxo.entry.block_daa_score,
+                    if utxo.entry.is_coinbase { "  coinbase" } else { "" },
+                    if *spendable { "" } else { "  immature" },
+                );
+            }
+            let total: u64 = utxos.iter().map(|(utxo, _)| utxo.entry.amount).sum();
+            println!("{} UTXOs, {} KAS", utxos.len(), format_kas_from_sompi(total));
+        }
+        Command::Serve | Command::Init { .. } | Command::Keygen { .. } | Command::Address => {
+            unreachable!("handled without a node")
+        }
     }
-
-    let tx_id = submit_faucet_transaction(
-        &state.client,
-        &state.faucet_address,
-        &destination,
-        state.amount_per_claim,
-        &state.faucet_private_key,
-    )
-    .await
-    .map_err(|e| {
-        error!("Faucet send failed: {e:?}");
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    Ok(Json(ClaimResponse {
-        transaction_id: tx_id.to_string(),
-        amount_kas: format_kas_from_sompi(state.amount_per_claim),
-        next_claim_seconds: state.claim_interval_seconds,
-    }))
+    Ok(())
 }
 
-async fn submit_faucet_transaction(
-    client: &GrpcClient,
-    faucet_address: &Address,
-    destination: &Address,
-    amount: u64,
-    private_key: &[u8; 32],
-) -> anyhow::Result<kaspa_rpc_core::RpcTransactionId> {
-    let utxos = client
-        .get_utxos_by_addresses(vec![faucet_address.clone()])
-        .await
-        .map_err(|e| anyhow::anyhow!("get_utxos_by_addresses failed: {e}"))?;
-
-    if utxos.is_empty() {
-        anyhow::bail!("Faucet has no UTXOs. Fund address {faucet_address} first.");
-    }
-
-    const FEE_PER_INPUT_SOMPI: u64 = 2000;
-    const DUST_SOMPI: u64 = 1000;
-
-    let mut selected = Vec::new();
-    let mut total_in: u64 = 0;
-
-    for entry in utxos.into_iter() {
-        let value = entry.utxo_entry.amount;
-        selected.push(entry);
-        total_in = total_in.saturating_add(value);
-
-        let fee = (selected.len() as u64 + 1) * FEE_PER_INPUT_SOMPI;
-        if total_in >= amount.saturating_add(fee) {
-            break;
+/// The faucet named by `--faucet`, or the only one configured.
+fn select_faucet(config: Config, path: Option<String>) -> anyhow::Result<FaucetConfig> {
+    let count = config.faucets.len();
+    match path {
+        Some(path) => {
+            let path = path.trim_matches('/');
+            config
+                .faucets
+                .into_iter()
+                .find(|f| f.path == path)
+                .ok_or_else(|| anyhow::anyhow!("No faucet is mounted at \"/{path}\""))
         }
+        None if count == 1 => Ok(config.faucets.into_iter().next().unwrap()),
+        None => anyhow::bail!("The config has {count} faucets; pick one with --faucet <path>"),
     }
+}
 
-    let fee = (selected.len() as u64 + 1) * FEE_PER_INPUT_SOMPI;
-    if total_in < amount.saturating_add(fee) {
-        anyhow::bail!(
-            "Insufficient faucet funds. Have {total_in} sompi, need {} sompi",
-            amount + fee
-        );
-    }
-
-    let mut change = total_in - amount - fee;
-    if change > 0 && change < DUST_SOMPI {
-        change = 0;
-    }
-
-    let inputs = selected
-        .iter()
-        .enumerate()
-        .map(|(i, e)| {
-            let outpoint = e.outpoint.into();
-            TransactionInput::new(outpoint, vec![], i as u64, 1)
-        })
-        .collect::<Vec<_>>();
-
-    let mut outputs = Vec::new();
-    outputs.push(TransactionOutput::new(amount, pay_to_address_script(destination)));
-    if change > 0 {
-        outputs.push(TransactionOutput::new(change, pay_to_address_script(faucet_address)));
-    }
-
-    let tx = Transaction::new(0, inputs, outputs, 0, SUBNETWORK_ID_NATIVE, 0, vec![]);
-    let entries = selected.into_iter().map(|e| UtxoEntry::from(e.utxo_entry)).collect::<Vec<_>>();
-    let signable_tx = SignableTransaction::with_entries(tx, entries);
-    let signed_tx = sign_with_multiple_v2(signable_tx, std::slice::from_ref(private_key)).fully_signed()?;
-
-    let rpc_transaction: RpcTransaction = signed_tx.tx.as_ref().into();
-    let tx_id = client.submit_transaction(rpc_transaction, false).await?;
-    Ok(tx_id)
+fn parse_address(address: &str) -> anyhow::Result<Address> {
+    Address::try_from(address).map_err(|e| anyhow::anyhow!("Invalid address {address}: {e}"))
 }
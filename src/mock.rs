@@ -0,0 +1,503 @@
+//! In-process stand-in for kaspad, for tests. It serves canned UTXOs and
+//! blocks, records submitted transactions and can be told to fail.
+
+use async_trait::async_trait;
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    future::BoxFuture,
+    stream::BoxStream,
+    FutureExt, StreamExt,
+};
+use kaspa_addresses::Address;
+use kaspa_consensus_core::{
+    header::Header,
+    network::NetworkId,
+    tx::{Transaction, TransactionOutpoint, UtxoEntry},
+};
+use kaspa_notify::{listener::ListenerId, scope::Scope};
+use kaspa_rpc_core::{
+    api::{connection::DynRpcConnection, rpc::RpcApi},
+    notify::connection::ChannelConnection,
+    *,
+};
+use kaspa_txscript::standard::pay_to_address_script;
+use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use crate::rpc::NodeRpc;
+
+pub struct MockNode {
+    network_id: NetworkId,
+    utxos: Mutex<Vec<RpcUtxosByAddressesEntry>>,
+    blocks: Mutex<HashMap<RpcHash, Header>>,
+    submitted: Mutex<Vec<Transaction>>,
+    submit_error: Mutex<Option<String>>,
+    synced: AtomicBool,
+    connected: AtomicBool,
+    notification_sender: UnboundedSender<Notification>,
+    notification_receiver: Mutex<Option<UnboundedReceiver<Notification>>>,
+}
+
+impl MockNode {
+    pub const VIRTUAL_DAA_SCORE: u64 = 1_000_000;
+
+    pub fn new(network_id: NetworkId) -> Arc<Self> {
+        let (notification_sender, notification_receiver) = unbounded();
+        Arc::new(Self {
+            network_id,
+            utxos: Mutex::new(Vec::new()),
+            blocks: Mutex::new(HashMap::new()),
+            submitted: Mutex::new(Vec::new()),
+            submit_error: Mutex::new(None),
+            synced: AtomicBool::new(true),
+            connected: AtomicBool::new(true),
+            notification_sender,
+            notification_receiver: Mutex::new(Some(notification_receiver)),
+        })
+    }
+
+    /// Adds a mature, non-coinbase UTXO of `amount` sompi paying `address`.
+    pub fn add_utxo(&self, address: &Address, amount: u64) {
+        let mut utxos = self.utxos.lock().unwrap();
+        let mut transaction_id = [0u8; 32];
+        transaction_id[..8].copy_from_slice(&(utxos.len() as u64 + 1).to_le_bytes());
+        utxos.push(RpcUtxosByAddressesEntry {
+            address: Some(address.clone()),
+            outpoint: TransactionOutpoint::new(RpcTransactionId::from_bytes(transaction_id), 0).into(),
+            utxo_entry: UtxoEntry::new(amount, pay_to_address_script(address), Self::VIRTUAL_DAA_SCORE - 100, false).into(),
+        });
+    }
+
+    /// Adds a block at `blue_score` for `get_block` to serve.
+    pub fn add_block(&self, hash: RpcHash, blue_score: u64) {
+        let mut header = Header::from_precomputed_hash(hash, vec![]);
+        header.daa_score = Self::VIRTUAL_DAA_SCORE;
+        header.blue_score = blue_score;
+        self.blocks.lock().unwrap().insert(hash, header);
+    }
+
+    /// Transactions accepted by `submit_transaction`, in order.
+    pub fn submitted(&self) -> Vec<Transaction> {
+        self.submitted.lock().unwrap().clone()
+    }
+
+    /// Makes `submit_transaction` fail with `reason`, or succeed again with `None`.
+    pub fn fail_submissions(&self, reason: Option<&str>) {
+        *self.submit_error.lock().unwrap() = reason.map(str::to_string);
+    }
+
+    pub fn set_synced(&self, synced: bool) {
+        self.synced.store(synced, Ordering::SeqCst);
+    }
+
+    pub fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::SeqCst);
+    }
+
+    /// Pushes a notification to whoever consumes `notifications()`.
+    pub fn notify(&self, notification: Notification) {
+        let _ = self.notification_sender.unbounded_send(notification);
+    }
+
+    fn check_connected(&self) -> RpcResult<()> {
+        if self.connected.load(Ordering::SeqCst) {
+            Ok(())
+        } else {
+            Err(RpcError::General("mock node is disconnected".to_string()))
+        }
+    }
+}
+
+impl NodeRpc for MockNode {
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    fn notifications(&self) -> BoxStream<'static, Notification> {
+        match self.notification_receiver.lock().unwrap().take() {
+            Some(receiver) => receiver.boxed(),
+            None => futures::stream::empty().boxed(),
+        }
+    }
+
+    fn disconnect(&self) -> BoxFuture<'_, ()> {
+        async move { self.set_connected(false) }.boxed()
+    }
+}
+
+#[async_trait]
+impl RpcApi for MockNode {
+    async fn get_info_call(&self, _connection: Option<&DynRpcConnection>, _request: GetInfoRequest) -> RpcResult<GetInfoResponse> {
+        self.check_connected()?;
+        Ok(GetInfoResponse {
+            p2p_id: "faucet-mock".to_string(),
+            mempool_size: 0,
+            server_version: "mock".to_string(),
+            is_utxo_indexed: true,
+            is_synced: self.synced.load(Ordering::SeqCst),
+            has_notify_command: true,
+            has_message_id: true,
+        })
+    }
+
+    async fn get_server_info_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetServerInfoRequest,
+    ) -> RpcResult<GetServerInfoResponse> {
+        self.check_connected()?;
+        Ok(GetServerInfoResponse {
+            rpc_api_version: 1,
+            rpc_api_revision: 0,
+            server_version: "mock".to_string(),
+            network_id: self.network_id,
+            has_utxo_index: true,
+            is_synced: self.synced.load(Ordering::SeqCst),
+            virtual_daa_score: Self::VIRTUAL_DAA_SCORE,
+        })
+    }
+
+    async fn get_connected_peer_info_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetConnectedPeerInfoRequest,
+    ) -> RpcResult<GetConnectedPeerInfoResponse> {
+        self.check_connected()?;
+        Ok(GetConnectedPeerInfoResponse { peer_info: vec![] })
+    }
+
+    async fn get_block_dag_info_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetBlockDagInfoRequest,
+    ) -> RpcResult<GetBlockDagInfoResponse> {
+        self.check_connected()?;
+        Ok(GetBlockDagInfoResponse::new(
+            self.network_id,
+            0,
+            0,
+            vec![],
+            1.0,
+            0,
+            vec![],
+            Default::default(),
+            Self::VIRTUAL_DAA_SCORE,
+            Default::default(),
+        ))
+    }
+
+    async fn get_utxos_by_addresses_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        request: GetUtxosByAddressesRequest,
+    ) -> RpcResult<GetUtxosByAddressesResponse> {
+        self.check_connected()?;
+        let entries = self
+            .utxos
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| entry.address.as_ref().is_some_and(|address| request.addresses.contains(address)))
+            .cloned()
+            .collect();
+        Ok(GetUtxosByAddressesResponse { entries })
+    }
+
+    async fn get_fee_estimate_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetFeeEstimateRequest,
+    ) -> RpcResult<GetFeeEstimateResponse> {
+        self.check_connected()?;
+        let bucket = RpcFeerateBucket { feerate: 1.0, estimated_seconds: 1.0 };
+        Ok(GetFeeEstimateResponse {
+            estimate: RpcFeeEstimate { priority_bucket: bucket, normal_buckets: vec![bucket], low_buckets: vec![bucket] },
+        })
+    }
+
+    async fn submit_transaction_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        request: SubmitTransactionRequest,
+    ) -> RpcResult<SubmitTransactionResponse> {
+        self.check_connected()?;
+        if let Some(reason) = self.submit_error.lock().unwrap().clone() {
+            return Err(RpcError::General(reason));
+        }
+        let transaction = Transaction::try_from(request.transaction)?;
+        let transaction_id = transaction.id();
+        self.submitted.lock().unwrap().push(transaction);
+        Ok(SubmitTransactionResponse { transaction_id })
+    }
+
+    async fn ping_call(&self, _connection: Option<&DynRpcConnection>, _request: PingRequest) -> RpcResult<PingResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_system_info_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetSystemInfoRequest,
+    ) -> RpcResult<GetSystemInfoResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_connections_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetConnectionsRequest,
+    ) -> RpcResult<GetConnectionsResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_metrics_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetMetricsRequest,
+    ) -> RpcResult<GetMetricsResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_sync_status_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetSyncStatusRequest,
+    ) -> RpcResult<GetSyncStatusResponse> {
+        Ok(GetSyncStatusResponse { is_synced: self.synced.load(Ordering::SeqCst) })
+    }
+
+    async fn get_current_network_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetCurrentNetworkRequest,
+    ) -> RpcResult<GetCurrentNetworkResponse> {
+        Ok(GetCurrentNetworkResponse { network: self.network_id.network_type })
+    }
+
+    async fn submit_block_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: SubmitBlockRequest,
+    ) -> RpcResult<SubmitBlockResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_block_template_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetBlockTemplateRequest,
+    ) -> RpcResult<GetBlockTemplateResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_peer_addresses_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetPeerAddressesRequest,
+    ) -> RpcResult<GetPeerAddressesResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_sink_call(&self, _connection: Option<&DynRpcConnection>, _request: GetSinkRequest) -> RpcResult<GetSinkResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_mempool_entry_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetMempoolEntryRequest,
+    ) -> RpcResult<GetMempoolEntryResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_mempool_entries_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetMempoolEntriesRequest,
+    ) -> RpcResult<GetMempoolEntriesResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn add_peer_call(&self, _connection: Option<&DynRpcConnection>, _request: AddPeerRequest) -> RpcResult<AddPeerResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn submit_transaction_replacement_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: SubmitTransactionReplacementRequest,
+    ) -> RpcResult<SubmitTransactionReplacementResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_block_call(&self, _connection: Option<&DynRpcConnection>, request: GetBlockRequest) -> RpcResult<GetBlockResponse> {
+        self.check_connected()?;
+        let header = self.blocks.lock().unwrap().get(&request.hash).cloned();
+        let header = header.ok_or_else(|| RpcError::General(format!("block {} not found", request.hash)))?;
+        Ok(GetBlockResponse {
+            block: RpcBlock {
+                header: (&header).into(),
+                transactions: vec![],
+                verbose_data: None,
+            },
+        })
+    }
+
+    async fn get_subnetwork_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetSubnetworkRequest,
+    ) -> RpcResult<GetSubnetworkResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_virtual_chain_from_block_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetVirtualChainFromBlockRequest,
+    ) -> RpcResult<GetVirtualChainFromBlockResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_blocks_call(&self, _connection: Option<&DynRpcConnection>, _request: GetBlocksRequest) -> RpcResult<GetBlocksResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_block_count_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetBlockCountRequest,
+    ) -> RpcResult<GetBlockCountResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn resolve_finality_conflict_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: ResolveFinalityConflictRequest,
+    ) -> RpcResult<ResolveFinalityConflictResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn shutdown_call(&self, _connection: Option<&DynRpcConnection>, _request: ShutdownRequest) -> RpcResult<ShutdownResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_headers_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetHeadersRequest,
+    ) -> RpcResult<GetHeadersResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_balance_by_address_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetBalanceByAddressRequest,
+    ) -> RpcResult<GetBalanceByAddressResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_balances_by_addresses_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetBalancesByAddressesRequest,
+    ) -> RpcResult<GetBalancesByAddressesResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_sink_blue_score_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetSinkBlueScoreRequest,
+    ) -> RpcResult<GetSinkBlueScoreResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn ban_call(&self, _connection: Option<&DynRpcConnection>, _request: BanRequest) -> RpcResult<BanResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn unban_call(&self, _connection: Option<&DynRpcConnection>, _request: UnbanRequest) -> RpcResult<UnbanResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn estimate_network_hashes_per_second_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: EstimateNetworkHashesPerSecondRequest,
+    ) -> RpcResult<EstimateNetworkHashesPerSecondResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_mempool_entries_by_addresses_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetMempoolEntriesByAddressesRequest,
+    ) -> RpcResult<GetMempoolEntriesByAddressesResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_coin_supply_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetCoinSupplyRequest,
+    ) -> RpcResult<GetCoinSupplyResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_daa_score_timestamp_estimate_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetDaaScoreTimestampEstimateRequest,
+    ) -> RpcResult<GetDaaScoreTimestampEstimateResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_fee_estimate_experimental_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetFeeEstimateExperimentalRequest,
+    ) -> RpcResult<GetFeeEstimateExperimentalResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_current_block_color_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetCurrentBlockColorRequest,
+    ) -> RpcResult<GetCurrentBlockColorResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    async fn get_utxo_return_address_call(
+        &self,
+        _connection: Option<&DynRpcConnection>,
+        _request: GetUtxoReturnAddressRequest,
+    ) -> RpcResult<GetUtxoReturnAddressResponse> {
+        Err(RpcError::NotImplemented)
+    }
+
+    // Notification API. Subscriptions are accepted and ignored; tests push
+    // notifications directly with `notify`.
+
+    fn register_new_listener(&self, _connection: ChannelConnection) -> ListenerId {
+        ListenerId::default()
+    }
+
+    async fn unregister_listener(&self, _id: ListenerId) -> RpcResult<()> {
+        Ok(())
+    }
+
+    async fn start_notify(&self, _id: ListenerId, _scope: Scope) -> RpcResult<()> {
+        self.check_connected()
+    }
+
+    async fn stop_notify(&self, _id: ListenerId, _scope: Scope) -> RpcResult<()> {
+        self.check_connected()
+    }
+}
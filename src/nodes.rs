@@ -0,0 +1,344 @@
+use kaspa_consensus_core::network::NetworkId;
+use kaspa_rpc_core::api::rpc::RpcApi;
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{error, info, warn};
+
+use crate::notify::Subscriber;
+use crate::rpc::{self, DynNodeRpc, Endpoint, NodeRpc};
+
+/// How often the supervisor checks the active node's connection.
+const SUPERVISOR_INTERVAL: Duration = Duration::from_secs(1);
+const MIN_RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(60);
+
+/// What the last health probe saw of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Health {
+    Unreachable,
+    WrongNetwork(NetworkId),
+    Syncing,
+    Synced,
+}
+
+/// Sync state of a node as of its last health probe.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SyncState {
+    pub is_synced: bool,
+    pub virtual_daa_score: u64,
+    pub peers: usize,
+}
+
+struct Node {
+    endpoint: Endpoint,
+    client: Mutex<Option<DynNodeRpc>>,
+    health: Mutex<Health>,
+    sync_state: Mutex<SyncState>,
+}
+
+impl Node {
+    fn client(&self) -> Option<DynNodeRpc> {
+        self.client.lock().unwrap().clone()
+    }
+
+    fn health(&self) -> Health {
+        *self.health.lock().unwrap()
+    }
+}
+
+/// The faucet's kaspad endpoints. Requests go to the active node; health
+/// checks move traffic and notification subscriptions to another synced
+/// node on the right network when the active one stops being one.
+///
+/// While the active node's connection is down the pool is degraded: the
+/// supervisor reconnects with backoff and resubscribes, and claims are
+/// refused until it succeeds.
+pub struct NodePool {
+    nodes: Vec<Node>,
+    active: AtomicUsize,
+    network: NetworkId,
+    subscriber: Subscriber,
+    /// Seconds until the next reconnect attempt while degraded, zero otherwise.
+    degraded_retry_after: AtomicU64,
+    /// Serializes failovers and resubscriptions.
+    switching: tokio::sync::Mutex<()>,
+}
+
+impl NodePool {
+    /// Connects to every endpoint and subscribes on the best one. Fails if
+    /// none is usable, or if any reachable node is on another network.
+    pub async fn start(endpoints: Vec<Endpoint>, network: NetworkId, subscriber: Subscriber) -> anyhow::Result<Arc<Self>> {
+        Self::start_with(endpoints.into_iter().map(|endpoint| (endpoint, None)).collect(), network, subscriber).await
+    }
+
+    /// Like `start`, over a single client that is already connected, such as
+    /// an in-process node. `endpoint` is used if it ever has to reconnect.
+    pub async fn with_client(
+        endpoint: Endpoint,
+        client: DynNodeRpc,
+        network: NetworkId,
+        subscriber: Subscriber,
+    ) -> anyhow::Result<Arc<Self>> {
+        Self::start_with(vec![(endpoint, Some(client))], network, subscriber).await
+    }
+
+    async fn start_with(
+        nodes: Vec<(Endpoint, Option<DynNodeRpc>)>,
+        network: NetworkId,
+        subscriber: Subscriber,
+    ) -> anyhow::Result<Arc<Self>> {
+        for client in nodes.iter().filter_map(|(_, client)| client.as_ref()) {
+            subscriber.spawn_listener(client);
+        }
+        let pool = Self {
+            nodes: nodes
+                .into_iter()
+                .map(|(endpoint, client)| Node {
+                    endpoint,
+                    client: Mutex::new(client),
+                    health: Mutex::new(Health::Unreachable),
+                    sync_state: Mutex::new(SyncState::default()),
+                })
+                .collect(),
+            active: AtomicUsize::new(0),
+            network,
+            subscriber,
+            degraded_retry_after: AtomicU64::new(0),
+            switching: tokio::sync::Mutex::new(()),
+        };
+        for node in &pool.nodes {
+            pool.probe(node).await;
+            if let Health::WrongNetwork(found) = node.health() {
+                anyhow::bail!(
+                    "kaspad at {} is on {}, but the faucet is configured for {}",
+                    node.endpoint.url,
+                    found,
+                    network
+                );
+            }
+        }
+
+        let active = pool
+            .pick()
+            .ok_or_else(|| anyhow::anyhow!("None of the configured kaspad endpoints is reachable"))?;
+        let node = &pool.nodes[active];
+        pool.subscriber.subscribe(&node.client().expect("picked node is connected")).await?;
+        pool.active.store(active, Ordering::SeqCst);
+        info!("Using kaspad at {} ({:?})", node.endpoint.url, node.health());
+        Ok(Arc::new(pool))
+    }
+
+    /// Client of the active node.
+    pub fn client(&self) -> DynNodeRpc {
+        self.nodes[self.active.load(Ordering::SeqCst)]
+            .client()
+            .expect("active node is connected")
+    }
+
+    pub fn active_url(&self) -> &str {
+        &self.nodes[self.active.load(Ordering::SeqCst)].endpoint.url
+    }
+
+    /// Sync state of the active node.
+    pub fn sync_state(&self) -> SyncState {
+        *self.nodes[self.active.load(Ordering::SeqCst)].sync_state.lock().unwrap()
+    }
+
+    /// While the active node is disconnected, the seconds until the next
+    /// reconnect attempt.
+    pub fn degraded(&self) -> Option<u64> {
+        match self.degraded_retry_after.load(Ordering::SeqCst) {
+            0 => None,
+            seconds => Some(seconds),
+        }
+    }
+
+    /// Disconnects from every node.
+    pub async fn disconnect(&self) {
+        for client in self.nodes.iter().filter_map(Node::client) {
+            client.disconnect().await;
+        }
+    }
+
+    /// Replaces `node`'s client with a fresh connection.
+    async fn reconnect(&self, node: &Node) -> anyhow::Result<DynNodeRpc> {
+        let client = rpc::connect(&node.endpoint).await?;
+        self.subscriber.spawn_listener(&client);
+        if let Some(old) = node.client.lock().unwrap().replace(client.clone()) {
+            tokio::spawn(async move {
+                old.disconnect().await;
+            });
+        }
+        Ok(client)
+    }
+
+    async fn probe(&self, node: &Node) {
+        let client = match node.client() {
+            Some(client) if client.is_connected() => client,
+            // The supervisor reconnects the active node, since it also has to resubscribe
+            Some(_) if std::ptr::eq(node, &self.nodes[self.active.load(Ordering::SeqCst)]) => {
+                *node.health.lock().unwrap() = Health::Unreachable;
+                return;
+            }
+            _ => match self.reconnect(node).await {
+                Ok(client) => client,
+                Err(e) => {
+                    warn!("kaspad at {} is unreachable: {e}", node.endpoint.url);
+                    *node.health.lock().unwrap() = Health::Unreachable;
+                    return;
+                }
+            },
+        };
+
+        let health = match client.get_server_info().await {
+            Ok(info) if info.network_id != self.network => Health::WrongNetwork(info.network_id),
+            Ok(info) => {
+                let peers = match client.get_connected_peer_info().await {
+                    Ok(response) => response.peer_info.len(),
+                    Err(e) => {
+                        warn!("Failed to fetch peers of kaspad at {}: {e}", node.endpoint.url);
+                        0
+                    }
+                };
+                *node.sync_state.lock().unwrap() = SyncState {
+                    is_synced: info.is_synced,
+                    virtual_daa_score: info.virtual_daa_score,
+                    peers,
+                };
+                if info.is_synced {
+                    Health::Synced
+                } else {
+                    Health::Syncing
+                }
+            }
+            Err(e) => {
+                warn!("Health probe of kaspad at {} failed: {e}", node.endpoint.url);
+                Health::Unreachable
+            }
+        };
+        if health != Health::Synced {
+            node.sync_state.lock().unwrap().is_synced = false;
+        }
+        let previous = std::mem::replace(&mut *node.health.lock().unwrap(), health);
+        if previous != health {
+            info!("kaspad at {} is now {:?}", node.endpoint.url, health);
+        }
+    }
+
+    /// The active node while it stays synced, otherwise the first synced
+    /// node. With none synced, a node that is at least syncing on the right
+    /// network, preferring the active one.
+    fn pick(&self) -> Option<usize> {
+        let active = self.active.load(Ordering::SeqCst);
+        let with = |health: Health| {
+            std::iter::once(active)
+                .chain(0..self.nodes.len())
+                .find(|&i| self.nodes[i].health() == health)
+        };
+        with(Health::Synced).or_else(|| with(Health::Syncing))
+    }
+
+    async fn check(&self) {
+        for node in &self.nodes {
+            self.probe(node).await;
+        }
+
+        let _switching = self.switching.lock().await;
+        let active = self.active.load(Ordering::SeqCst);
+        let Some(next) = self.pick() else {
+            error!("No usable kaspad endpoint; staying on {}", self.nodes[active].endpoint.url);
+            return;
+        };
+        if next == active {
+            return;
+        }
+
+        let (from, to) = (&self.nodes[active], &self.nodes[next]);
+        warn!("Failing over from kaspad at {} to {}", from.endpoint.url, to.endpoint.url);
+        if let Some(client) = from.client() {
+            if let Err(e) = self.subscriber.unsubscribe(&client).await {
+                warn!("Failed to unsubscribe from {}: {e}", from.endpoint.url);
+            }
+        }
+        let client = to.client().expect("picked node is connected");
+        match self.subscriber.subscribe(&client).await {
+            Ok(()) => {
+                self.active.store(next, Ordering::SeqCst);
+                self.degraded_retry_after.store(0, Ordering::SeqCst);
+            }
+            Err(e) => {
+                error!("Failed to subscribe on {}: {e}", to.endpoint.url);
+                *to.health.lock().unwrap() = Health::Unreachable;
+                if let Some(client) = from.client() {
+                    if let Err(e) = self.subscriber.subscribe(&client).await {
+                        error!("Failed to resubscribe on {}: {e}", from.endpoint.url);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reconnects the active node if needed and resubscribes on it.
+    async fn recover(&self) -> anyhow::Result<()> {
+        let _switching = self.switching.lock().await;
+        if self.degraded().is_none() {
+            // A failover got there first
+            return Ok(());
+        }
+        let node = &self.nodes[self.active.load(Ordering::SeqCst)];
+        let client = match node.client() {
+            Some(client) if client.is_connected() => client,
+            _ => self.reconnect(node).await?,
+        };
+        self.subscriber.subscribe(&client).await?;
+        self.degraded_retry_after.store(0, Ordering::SeqCst);
+        info!("Reconnected to kaspad at {}", node.endpoint.url);
+        Ok(())
+    }
+}
+
+/// Watches the active node's connection. When it drops, marks the pool
+/// degraded and reconnects with exponential backoff, resubscribing to
+/// notifications (which a new connection doesn't carry over).
+pub fn spawn_supervisor(pool: Arc<NodePool>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut backoff = MIN_RECONNECT_BACKOFF;
+        loop {
+            if pool.degraded().is_none() {
+                tokio::time::sleep(SUPERVISOR_INTERVAL).await;
+                if pool.client().is_connected() {
+                    continue;
+                }
+                warn!("Lost connection to kaspad at {}; claims are paused", pool.active_url());
+                backoff = MIN_RECONNECT_BACKOFF;
+                pool.degraded_retry_after.store(backoff.as_secs(), Ordering::SeqCst);
+            }
+
+            tokio::time::sleep(backoff).await;
+            if let Err(e) = pool.recover().await {
+                backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+                warn!("Reconnecting to kaspad at {} failed, retrying in {:?}: {e}", pool.active_url(), backoff);
+                if pool.degraded().is_some() {
+                    pool.degraded_retry_after.store(backoff.as_secs(), Ordering::SeqCst);
+                }
+            }
+        }
+    })
+}
+
+/// Probes every node each `period` and fails over when needed.
+pub fn spawn_health_checks(pool: Arc<NodePool>, period: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            pool.check().await;
+        }
+    })
+}
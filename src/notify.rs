@@ -0,0 +1,131 @@
+use kaspa_addresses::Address;
+use futures::StreamExt;
+use kaspa_notify::{
+    listener::ListenerId,
+    scope::{
+        Scope, SinkBlueScoreChangedScope, UtxosChangedScope, VirtualChainChangedScope,
+        VirtualDaaScoreChangedScope,
+    },
+};
+use kaspa_rpc_core::{api::rpc::RpcApi, Notification};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::rpc::{DynNodeRpc, NodeRpc};
+use crate::tracker::ClaimTracker;
+use crate::utxo::{Utxo, UtxoManager};
+
+/// Keeps the faucet's UTXO set and claim tracker fed from a node's
+/// notifications: UTXO changes of the faucet address, virtual DAA score
+/// changes (for coinbase maturity) and virtual chain and sink blue score
+/// changes (for claim confirmations).
+///
+/// Clients run in direct notification mode, so notifications arrive on
+/// their channel without going through a listener.
+#[derive(Clone)]
+pub struct Subscriber {
+    faucet_address: Address,
+    utxo_manager: Arc<UtxoManager>,
+    tracker: Arc<ClaimTracker>,
+}
+
+impl Subscriber {
+    pub fn new(faucet_address: Address, utxo_manager: Arc<UtxoManager>, tracker: Arc<ClaimTracker>) -> Self {
+        Self {
+            faucet_address,
+            utxo_manager,
+            tracker,
+        }
+    }
+
+    fn scopes(&self) -> Vec<Scope> {
+        vec![
+            Scope::UtxosChanged(UtxosChangedScope::new(vec![self.faucet_address.clone()])),
+            Scope::VirtualDaaScoreChanged(VirtualDaaScoreChangedScope::default()),
+            Scope::VirtualChainChanged(VirtualChainChangedScope::new(true)),
+            Scope::SinkBlueScoreChanged(SinkBlueScoreChangedScope::default()),
+        ]
+    }
+
+    /// Subscribes on `client`, then seeds the UTXO set and DAA score with a
+    /// snapshot from it.
+    pub async fn subscribe(&self, client: &dyn NodeRpc) -> anyhow::Result<()> {
+        for scope in self.scopes() {
+            client.start_notify(ListenerId::default(), scope).await?;
+        }
+
+        let dag_info = client.get_block_dag_info().await?;
+        self.utxo_manager.set_virtual_daa_score(dag_info.virtual_daa_score);
+        self.tracker.set_sink(dag_info.sink);
+
+        self.utxo_manager.begin_sync();
+        let utxos = client
+            .get_utxos_by_addresses(vec![self.faucet_address.clone()])
+            .await
+            .map_err(|e| anyhow::anyhow!("get_utxos_by_addresses failed: {e}"))?;
+        info!("Loaded {} faucet UTXOs", utxos.len());
+        self.utxo_manager.sync(utxos.into_iter().map(Utxo::from).collect());
+        Ok(())
+    }
+
+    pub async fn unsubscribe(&self, client: &dyn NodeRpc) -> anyhow::Result<()> {
+        for scope in self.scopes() {
+            client.stop_notify(ListenerId::default(), scope).await?;
+        }
+        Ok(())
+    }
+
+    /// Pumps `client`'s notification channel until it closes. Only needed
+    /// once per client; it is idle while the client has no subscriptions.
+    pub fn spawn_listener(&self, client: &DynNodeRpc) {
+        spawn_listener(client, self.utxo_manager.clone(), self.tracker.clone());
+    }
+}
+
+fn spawn_listener(client: &DynNodeRpc, utxo_manager: Arc<UtxoManager>, tracker: Arc<ClaimTracker>) {
+    let mut notifications = client.notifications();
+    let client = client.clone();
+    tokio::spawn(async move {
+        while let Some(notification) = notifications.next().await {
+            match notification {
+                Notification::UtxosChanged(n) => {
+                    let added = n.added.iter().cloned().map(Utxo::from).collect();
+                    let removed = n.removed.iter().cloned().map(Utxo::from).collect();
+                    utxo_manager.apply_changes(added, removed);
+                }
+                Notification::VirtualDaaScoreChanged(n) => {
+                    utxo_manager.set_virtual_daa_score(n.virtual_daa_score);
+                }
+                Notification::VirtualChainChanged(n) => {
+                    tracker.unaccept(&n.removed_chain_block_hashes);
+                    if let Some(sink) = n.added_chain_block_hashes.last() {
+                        tracker.set_sink(*sink);
+                    }
+                    for accepted in n.accepted_transaction_ids.iter() {
+                        let tx_ids = tracker.tracked(&accepted.accepted_transaction_ids);
+                        if tx_ids.is_empty() {
+                            continue;
+                        }
+                        let header = client
+                            .get_block(accepted.accepting_block_hash, false)
+                            .await
+                            .map(|block| block.header)
+                            .map_err(|e| warn!("Failed to fetch accepting block: {e}"))
+                            .ok();
+                        tracker.accept(
+                            accepted.accepting_block_hash,
+                            header.as_ref().map(|h| h.daa_score),
+                            header.as_ref().map(|h| h.blue_score),
+                            &tx_ids,
+                        );
+                    }
+                }
+                Notification::SinkBlueScoreChanged(n) => {
+                    tracker.sink_blue_score_changed(n.sink_blue_score);
+                }
+                _ => {}
+            }
+        }
+        warn!("Notification channel closed; faucet UTXO set is no longer updated");
+    });
+}
@@ -1,28 +1,122 @@
-use std::collections::HashMap;
-use std::sync::Mutex;
-use std::time::{Duration, Instant};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use crate::ledger::{unix_now, ClaimRecord, Ledger};
+
+/// Which of the independent claim limits rejected a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Ip,
+    Address,
+}
+
+#[derive(Debug)]
+pub enum TryClaimError {
+    RateLimited { limit: Limit, retry_after_seconds: u64 },
+    Ledger(anyhow::Error),
+}
+
+impl From<anyhow::Error> for TryClaimError {
+    fn from(e: anyhow::Error) -> Self {
+        TryClaimError::Ledger(e)
+    }
+}
+
+/// Rate limiter backed by the claim ledger, so limits survive restarts.
+///
+/// A claim must pass both the per-IP and the per-destination-address limit.
+/// Claims that are still being processed are tracked in memory so that two
+/// concurrent requests for the same IP or address cannot both pass the
+/// ledger check.
 pub struct RateLimiter {
-    claims: Mutex<HashMap<String, Instant>>,
-    interval: Duration,
+    ledger: Arc<Ledger>,
+    in_flight: Mutex<InFlight>,
+    ip_interval: Duration,
+    address_interval: Duration,
+}
+
+#[derive(Default)]
+struct InFlight {
+    ips: HashSet<String>,
+    addresses: HashSet<String>,
+}
+
+/// Held while a claim is in progress. Dropping it without calling
+/// [`ClaimPermit::record`] leaves the ledger untouched, so a failed send
+/// does not count against the caller.
+pub struct ClaimPermit<'a> {
+    limiter: &'a RateLimiter,
+    ip: String,
+    address: String,
 }
 
 impl RateLimiter {
-    pub fn new(interval: Duration) -> Self {
+    pub fn new(ledger: Arc<Ledger>, ip_interval: Duration, address_interval: Duration) -> Self {
         Self {
-            claims: Mutex::new(HashMap::new()),
-            interval,
+            ledger,
+            in_flight: Mutex::new(InFlight::default()),
+            ip_interval,
+            address_interval,
         }
     }
 
-    pub fn try_claim(&self, ip: &str) -> bool {
-        let mut claims = self.claims.lock().unwrap();
-        if let Some(last) = claims.get(ip) {
-            if last.elapsed() < self.interval {
-                return false;
-            }
+    pub fn try_claim(&self, ip: &str, address: &str) -> Result<ClaimPermit<'_>, TryClaimError> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let now = unix_now();
+
+        if in_flight.ips.contains(ip) {
+            return Err(self.limited(Limit::Ip, None, now));
+        }
+        let last_ip = self.ledger.last_claim_by_ip(ip)?;
+        if let Some(last) = last_ip.filter(|t| now.saturating_sub(*t) < self.ip_interval.as_secs()) {
+            return Err(self.limited(Limit::Ip, Some(last), now));
         }
-        claims.insert(ip.to_string(), Instant::now());
-        true
+
+        if in_flight.addresses.contains(address) {
+            return Err(self.limited(Limit::Address, None, now));
+        }
+        let last_address = self.ledger.last_claim_by_address(address)?;
+        if let Some(last) =
+            last_address.filter(|t| now.saturating_sub(*t) < self.address_interval.as_secs())
+        {
+            return Err(self.limited(Limit::Address, Some(last), now));
+        }
+
+        in_flight.ips.insert(ip.to_string());
+        in_flight.addresses.insert(address.to_string());
+        Ok(ClaimPermit {
+            limiter: self,
+            ip: ip.to_string(),
+            address: address.to_string(),
+        })
+    }
+
+    /// Builds the rejection for `limit`. Without a ledger timestamp (the claim
+    /// is still in flight) the caller is told to wait a full interval.
+    fn limited(&self, limit: Limit, last_claim: Option<u64>, now: u64) -> TryClaimError {
+        let interval = match limit {
+            Limit::Ip => self.ip_interval.as_secs(),
+            Limit::Address => self.address_interval.as_secs(),
+        };
+        let elapsed = last_claim.map(|t| now.saturating_sub(t)).unwrap_or(0);
+        TryClaimError::RateLimited {
+            limit,
+            retry_after_seconds: interval.saturating_sub(elapsed),
+        }
+    }
+}
+
+impl ClaimPermit<'_> {
+    pub fn record(self, claim: &ClaimRecord) -> anyhow::Result<()> {
+        self.limiter.ledger.record_claim(claim)
+    }
+}
+
+impl Drop for ClaimPermit<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.limiter.in_flight.lock().unwrap();
+        in_flight.ips.remove(&self.ip);
+        in_flight.addresses.remove(&self.address);
     }
 }
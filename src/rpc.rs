@@ -0,0 +1,123 @@
+use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
+use kaspa_grpc_client::GrpcClient;
+use kaspa_rpc_core::{api::rpc::RpcApi, notify::mode::NotificationMode, Notification};
+use kaspa_wrpc_client::{
+    client::{ConnectOptions, ConnectStrategy},
+    KaspaRpcClient, WrpcEncoding,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// How the faucet talks to kaspad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Transport {
+    Grpc,
+    WrpcBorsh,
+    WrpcJson,
+}
+
+/// A kaspad URL and the transport to reach it with.
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub url: String,
+    pub transport: Transport,
+}
+
+/// A node connection over any transport. Everything the faucet asks of
+/// kaspad goes through `RpcApi`; this adds the connection plumbing the
+/// transports don't share a trait for.
+pub trait NodeRpc: RpcApi {
+    fn is_connected(&self) -> bool;
+
+    /// Notifications for subscriptions made with `ListenerId::default()`.
+    fn notifications(&self) -> BoxStream<'static, Notification>;
+
+    fn disconnect(&self) -> BoxFuture<'_, ()>;
+}
+
+pub type DynNodeRpc = Arc<dyn NodeRpc>;
+
+impl NodeRpc for GrpcClient {
+    fn is_connected(&self) -> bool {
+        GrpcClient::is_connected(self)
+    }
+
+    fn notifications(&self) -> BoxStream<'static, Notification> {
+        self.notification_channel_receiver().boxed()
+    }
+
+    fn disconnect(&self) -> BoxFuture<'_, ()> {
+        async move {
+            let _ = GrpcClient::disconnect(self).await;
+        }
+        .boxed()
+    }
+}
+
+impl NodeRpc for KaspaRpcClient {
+    fn is_connected(&self) -> bool {
+        KaspaRpcClient::is_connected(self)
+    }
+
+    fn notifications(&self) -> BoxStream<'static, Notification> {
+        self.notification_channel_receiver().boxed()
+    }
+
+    fn disconnect(&self) -> BoxFuture<'_, ()> {
+        async move {
+            let _ = KaspaRpcClient::disconnect(self).await;
+        }
+        .boxed()
+    }
+}
+
+/// Connects to `endpoint`. Clients don't reconnect on their own; the node
+/// supervisor does, so it can resubscribe.
+pub async fn connect(endpoint: &Endpoint) -> anyhow::Result<DynNodeRpc> {
+    info!("Connecting to kaspad at: {} ({:?})", endpoint.url, endpoint.transport);
+    match endpoint.transport {
+        Transport::Grpc => Ok(Arc::new(connect_grpc(&endpoint.url).await?)),
+        Transport::WrpcBorsh => Ok(Arc::new(connect_wrpc(&endpoint.url, WrpcEncoding::Borsh).await?)),
+        Transport::WrpcJson => Ok(Arc::new(connect_wrpc(&endpoint.url, WrpcEncoding::SerdeJson).await?)),
+    }
+}
+
+async fn connect_grpc(url: &str) -> anyhow::Result<GrpcClient> {
+    match GrpcClient::connect_with_args(
+        NotificationMode::Direct,
+        url.to_string(),
+        None,
+        false,
+        None,
+        false,
+        Some(500_000),
+        Default::default(),
+    )
+    .await
+    {
+        Ok(c) => {
+            c.start(None).await;
+            Ok(c)
+        }
+        Err(e) => {
+            warn!("connect_with_args failed, falling back to connect(): {:?}", e);
+            let c = GrpcClient::connect(url.to_string()).await?;
+            c.start(None).await;
+            Ok(c)
+        }
+    }
+}
+
+async fn connect_wrpc(url: &str, encoding: WrpcEncoding) -> anyhow::Result<KaspaRpcClient> {
+    let client = KaspaRpcClient::new(encoding, Some(url), None, None, None)?;
+    client
+        .connect(Some(ConnectOptions {
+            block_async_connect: true,
+            strategy: ConnectStrategy::Fallback,
+            ..Default::default()
+        }))
+        .await?;
+    Ok(client)
+}
@@ -0,0 +1,66 @@
+//! When the builder may fold change into the fee, when it must refuse, and
+//! how greedy selection gets past a refusal.
+
+use kaspa_consensus_core::tx::{ScriptPublicKey, TransactionId, TransactionOutpoint, TransactionOutput, UtxoEntry};
+
+use super::network;
+use crate::builder::{build_transaction, select_greedy, BuildError};
+use crate::fees::FeeCalculator;
+use crate::utxo::Utxo;
+
+const SOMPI_PER_KAS: u64 = 100_000_000;
+
+fn script(tag: u8) -> ScriptPublicKey {
+    ScriptPublicKey::from_vec(0, [vec![0x20], vec![tag; 32], vec![0xac]].concat())
+}
+
+fn inputs(count: u64, amount: u64) -> Vec<Utxo> {
+    (0..count).map(|i| utxo(i + 1, amount)).collect()
+}
+
+fn utxo(id: u64, amount: u64) -> Utxo {
+    Utxo {
+        outpoint: TransactionOutpoint::new(TransactionId::from_u64_word(id), 0),
+        entry: UtxoEntry::new(amount, script(1), 0, false),
+    }
+}
+
+#[test]
+fn large_change_over_the_mass_limit_is_refused_not_burned() {
+    // Enough inputs that compute mass alone is over the limit
+    let inputs = inputs(200, 10 * SOMPI_PER_KAS);
+    let payments = [TransactionOutput::new(SOMPI_PER_KAS, script(2))];
+
+    match build_transaction(&inputs, &payments, &script(1), &FeeCalculator::new(network(), 1.0)) {
+        Err(BuildError::TooLarge { .. }) => {}
+        Err(e) => panic!("expected TooLarge, got {e}"),
+        Ok(unsigned) => panic!("built a transaction with fee {} and change {:?}", unsigned.fee, unsigned.change),
+    }
+}
+
+#[test]
+fn uneconomic_change_goes_to_the_fee() {
+    let inputs = inputs(1, 10 * SOMPI_PER_KAS);
+    // Leaves a few thousand sompi, whose storage mass alone is over the limit
+    let payments = [TransactionOutput::new(10 * SOMPI_PER_KAS - 5_000, script(2))];
+
+    let unsigned = build_transaction(&inputs, &payments, &script(1), &FeeCalculator::new(network(), 1.0)).unwrap();
+    assert!(unsigned.change.is_none());
+    assert_eq!(unsigned.fee, 5_000);
+}
+
+#[test]
+fn greedy_selection_adds_inputs_until_the_change_fits() {
+    // The first input alone leaves 0.3 KAS of change, worth keeping but too
+    // heavy next to a 0.1 KAS payment; the second makes the change large
+    let candidates = vec![utxo(1, 4 * SOMPI_PER_KAS / 10), utxo(2, 100 * SOMPI_PER_KAS)];
+    let payments = [TransactionOutput::new(SOMPI_PER_KAS / 10, script(2))];
+    let fees = FeeCalculator::new(network(), 1.0);
+    assert!(matches!(
+        build_transaction(&candidates[..1], &payments, &script(1), &fees),
+        Err(BuildError::TooLarge { .. })
+    ));
+
+    let selected = select_greedy(candidates, &payments, &script(1), &fees).unwrap();
+    assert_eq!(selected.len(), 2);
+}
@@ -0,0 +1,107 @@
+//! The `Faucet` API on its own, without HTTP.
+
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_txscript::standard::pay_to_address_script;
+
+use super::{config, destination, start_faucet, start_faucet_with, AMOUNT_PER_CLAIM};
+use crate::config::FaucetConfig;
+use crate::error::FaucetError;
+use crate::rpc::NodeRpc;
+
+#[tokio::test]
+async fn send_pays_and_keeps_change() {
+    let (faucet, mock) = start_faucet(Some(1_000 * AMOUNT_PER_CLAIM), |_| {}).await;
+    assert_eq!(faucet.balance().spendable, 1_000 * AMOUNT_PER_CLAIM);
+    let to = destination(20);
+
+    let tx_id = faucet.send(&to, 3 * AMOUNT_PER_CLAIM).await.unwrap();
+
+    let submitted = mock.submitted();
+    assert_eq!(submitted.len(), 1);
+    assert_eq!(submitted[0].id(), tx_id);
+    assert!(submitted[0]
+        .outputs
+        .iter()
+        .any(|o| o.value == 3 * AMOUNT_PER_CLAIM && o.script_public_key == pay_to_address_script(&to)));
+    // The spent UTXO is gone and the change is tracked until the node reports it
+    let fee = 1_000 * AMOUNT_PER_CLAIM - submitted[0].outputs.iter().map(|o| o.value).sum::<u64>();
+    assert_eq!(faucet.balance().spendable, 997 * AMOUNT_PER_CLAIM - fee);
+}
+
+#[tokio::test]
+async fn send_checks_network_and_sync() {
+    let (faucet, mock) = start_faucet(Some(1_000 * AMOUNT_PER_CLAIM), |mock| mock.set_synced(false)).await;
+    assert!(!faucet.status().active);
+    assert!(matches!(
+        faucet.send(&destination(21), AMOUNT_PER_CLAIM).await,
+        Err(FaucetError::NodeNotSynced)
+    ));
+    assert!(mock.submitted().is_empty());
+
+    let (faucet, mock) = start_faucet(Some(1_000 * AMOUNT_PER_CLAIM), |_| {}).await;
+    assert!(faucet.status().active);
+    let mainnet = Address::new(Prefix::Mainnet, Version::PubKey, &[21; 32]);
+    assert!(matches!(
+        faucet.send(&mainnet, AMOUNT_PER_CLAIM).await,
+        Err(FaucetError::WrongNetwork { .. })
+    ));
+    assert!(mock.submitted().is_empty());
+}
+
+#[tokio::test]
+async fn batch_over_the_mass_limit_is_split() {
+    let (faucet, mock) = start_faucet(Some(1_000 * AMOUNT_PER_CLAIM), |_| {}).await;
+    // Each 1 KAS output carries about 10^4 storage mass, so these can't share one transaction
+    let claims = (30..45u8).map(destination).collect::<Vec<_>>();
+
+    let results = futures::future::join_all(claims.iter().map(|to| faucet.send(to, AMOUNT_PER_CLAIM))).await;
+
+    assert!(results.iter().all(|r| r.is_ok()), "{results:?}");
+    let submitted = mock.submitted();
+    assert!(submitted.len() > 1);
+    for to in &claims {
+        let script = pay_to_address_script(to);
+        let paid = submitted
+            .iter()
+            .flat_map(|tx| &tx.outputs)
+            .filter(|o| o.script_public_key == script && o.value == AMOUNT_PER_CLAIM)
+            .count();
+        assert_eq!(paid, 1, "{to} paid {paid} times");
+    }
+}
+
+#[tokio::test]
+async fn zero_amount_is_refused_before_queuing() {
+    let (faucet, mock) = start_faucet(Some(1_000 * AMOUNT_PER_CLAIM), |_| {}).await;
+
+    assert!(matches!(
+        faucet.send(&destination(50), 0).await,
+        Err(FaucetError::AmountTooSmall(_))
+    ));
+    // The batch worker is still alive for the next claim
+    faucet.send(&destination(51), AMOUNT_PER_CLAIM).await.unwrap();
+    assert_eq!(mock.submitted().len(), 1);
+}
+
+#[tokio::test]
+async fn shutdown_stops_the_workers_and_disconnects() {
+    let (faucet, mock) = start_faucet(Some(1_000 * AMOUNT_PER_CLAIM), |_| {}).await;
+
+    faucet.shutdown().await;
+
+    assert!(!mock.is_connected());
+    assert!(faucet.send(&destination(60), AMOUNT_PER_CLAIM).await.is_err());
+    assert!(mock.submitted().is_empty());
+}
+
+#[tokio::test]
+async fn given_node_needs_no_kaspad_urls() {
+    let config = FaucetConfig {
+        kaspad_urls: Vec::new(),
+        ..config()
+    };
+    let (faucet, mock) = start_faucet_with(&config, Some(1_000 * AMOUNT_PER_CLAIM), |_| {}).await;
+
+    faucet.send(&destination(70), AMOUNT_PER_CLAIM).await.unwrap();
+    assert_eq!(mock.submitted().len(), 1);
+}
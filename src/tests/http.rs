@@ -0,0 +1,269 @@
+//! HTTP-level tests of a faucet running against `MockNode`.
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_rpc_core::{
+    Notification, RpcAcceptedTransactionIds, RpcHash, RpcTransactionId, SinkBlueScoreChangedNotification,
+    VirtualChainChangedNotification,
+};
+use kaspa_txscript::standard::pay_to_address_script;
+use serde_json::{json, Value};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tower::ServiceExt;
+
+use super::{destination, start_faucet, AMOUNT_PER_CLAIM};
+use crate::http;
+use crate::mock::MockNode;
+
+async fn faucet(fund: Option<u64>) -> (Router, Arc<MockNode>) {
+    faucet_with(fund, |_| {}).await
+}
+
+async fn faucet_with(fund: Option<u64>, setup: impl FnOnce(&MockNode)) -> (Router, Arc<MockNode>) {
+    let (faucet, mock) = start_faucet(fund, setup).await;
+    (http::router(faucet), mock)
+}
+
+async fn claim(app: &Router, ip: [u8; 4], address: &str) -> (StatusCode, Option<String>, Value) {
+    let mut request = Request::post("/claim")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(json!({ "address": address }).to_string()))
+        .unwrap();
+    request.extensions_mut().insert(ConnectInfo(SocketAddr::from((ip, 40000))));
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .map(|v| v.to_str().unwrap().to_string());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, retry_after, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn claim_pays_destination() {
+    let (app, mock) = faucet(Some(1_000 * AMOUNT_PER_CLAIM)).await;
+    let to = destination(1);
+
+    let (status, _, body) = claim(&app, [10, 0, 0, 1], &to.to_string()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let submitted = mock.submitted();
+    assert_eq!(submitted.len(), 1);
+    let tx = &submitted[0];
+    assert_eq!(body["transaction_id"], tx.id().to_string());
+    assert!(tx
+        .outputs
+        .iter()
+        .any(|o| o.value == AMOUNT_PER_CLAIM && o.script_public_key == pay_to_address_script(&to)));
+}
+
+#[tokio::test]
+async fn second_claim_from_same_ip_is_rate_limited() {
+    let (app, mock) = faucet(Some(1_000 * AMOUNT_PER_CLAIM)).await;
+
+    let (status, _, _) = claim(&app, [10, 0, 0, 2], &destination(2).to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, retry_after, body) = claim(&app, [10, 0, 0, 2], &destination(3).to_string()).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], "rate_limited_ip");
+    assert!(retry_after.is_some());
+    assert_eq!(mock.submitted().len(), 1);
+}
+
+#[tokio::test]
+async fn second_claim_to_same_address_is_rate_limited() {
+    let (app, _) = faucet(Some(1_000 * AMOUNT_PER_CLAIM)).await;
+    let to = destination(4).to_string();
+
+    let (status, _, _) = claim(&app, [10, 0, 0, 3], &to).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, body) = claim(&app, [10, 0, 0, 4], &to).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], "rate_limited_address");
+}
+
+#[tokio::test]
+async fn unfunded_faucet_reports_insufficient_funds() {
+    let (app, mock) = faucet(None).await;
+
+    let (status, _, body) = claim(&app, [10, 0, 0, 5], &destination(5).to_string()).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["code"], "insufficient_funds");
+    assert!(mock.submitted().is_empty());
+}
+
+#[tokio::test]
+async fn claim_larger_than_balance_reports_insufficient_funds() {
+    let (app, _) = faucet(Some(AMOUNT_PER_CLAIM / 2)).await;
+
+    let (status, _, body) = claim(&app, [10, 0, 0, 6], &destination(6).to_string()).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["code"], "insufficient_funds");
+}
+
+#[tokio::test]
+async fn malformed_address_is_rejected() {
+    let (app, mock) = faucet(Some(1_000 * AMOUNT_PER_CLAIM)).await;
+
+    let (status, _, body) = claim(&app, [10, 0, 0, 7], "kaspatest:not-an-address").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_address");
+    assert!(mock.submitted().is_empty());
+}
+
+#[tokio::test]
+async fn mainnet_address_is_rejected() {
+    let (app, _) = faucet(Some(1_000 * AMOUNT_PER_CLAIM)).await;
+    let mainnet = Address::new(Prefix::Mainnet, Version::PubKey, &[8; 32]);
+
+    let (status, _, body) = claim(&app, [10, 0, 0, 8], &mainnet.to_string()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "wrong_network");
+}
+
+#[tokio::test]
+async fn rejected_submission_is_reported() {
+    let (app, mock) = faucet(Some(1_000 * AMOUNT_PER_CLAIM)).await;
+    mock.fail_submissions(Some("transaction is an orphan"));
+
+    let (status, _, body) = claim(&app, [10, 0, 0, 9], &destination(9).to_string()).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body["code"], "submit_rejected");
+
+    // A failed claim doesn't use up the rate limit
+    mock.fail_submissions(None);
+    let (status, _, _) = claim(&app, [10, 0, 0, 9], &destination(9).to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn unsynced_node_refuses_claims() {
+    let (app, mock) = faucet_with(Some(1_000 * AMOUNT_PER_CLAIM), |mock| mock.set_synced(false)).await;
+
+    let (status, _, body) = claim(&app, [10, 0, 0, 10], &destination(10).to_string()).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["code"], "node_not_synced");
+    assert!(mock.submitted().is_empty());
+}
+
+#[tokio::test]
+async fn abandoned_claim_still_counts_against_the_limits() {
+    let (app, mock) = faucet(Some(1_000 * AMOUNT_PER_CLAIM)).await;
+    let mut request = Request::post("/claim")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(json!({ "address": destination(11).to_string() }).to_string()))
+        .unwrap();
+    request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 11], 40000))));
+
+    // The client hangs up while the claim waits for its batch
+    assert!(tokio::time::timeout(Duration::ZERO, app.clone().oneshot(request)).await.is_err());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(mock.submitted().len(), 1);
+
+    let (status, _, body) = claim(&app, [10, 0, 0, 11], &destination(12).to_string()).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], "rate_limited_ip");
+}
+
+#[tokio::test]
+async fn lagging_event_stream_catches_up_and_ends() {
+    let (faucet, _) = start_faucet(None, |_| {}).await;
+    let app = http::router(faucet.clone());
+    let tx_id = RpcTransactionId::from_u64_word(1);
+    faucet.tracker.submitting(tx_id, 0);
+
+    let request = Request::get(format!("/claim/{tx_id}/events")).body(Body::empty()).unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // Other claims overflow the shared event channel before ours is rejected
+    for i in 2..200 {
+        faucet.tracker.submitting(RpcTransactionId::from_u64_word(i), 0);
+    }
+    faucet.tracker.rejected(tx_id, "transaction is an orphan".to_string());
+
+    let body = tokio::time::timeout(Duration::from_secs(5), axum::body::to_bytes(response.into_body(), usize::MAX))
+        .await
+        .expect("the stream should end after the terminal event")
+        .unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("event: rejected"));
+}
+
+#[tokio::test]
+async fn malformed_body_is_rejected_with_a_code() {
+    let (app, mock) = faucet(Some(1_000 * AMOUNT_PER_CLAIM)).await;
+    let mut request = Request::post("/claim")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from("{\"addr\":"))
+        .unwrap();
+    request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 13], 40000))));
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["code"], "invalid_request");
+    assert!(mock.submitted().is_empty());
+}
+
+async fn claim_status(app: &Router, tx_id: &str) -> Value {
+    let request = Request::get(format!("/claim/{tx_id}")).body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+/// Polls `GET /claim/{tx_id}` until the claim reaches `status`.
+async fn wait_for_status(app: &Router, tx_id: &str, status: &str) -> Value {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let body = claim_status(app, tx_id).await;
+            if body["status"] == status {
+                return body;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("claim {tx_id} never reached {status}"))
+}
+
+#[tokio::test]
+async fn claim_is_confirmed_by_node_notifications() {
+    let (app, mock) = faucet(Some(1_000 * AMOUNT_PER_CLAIM)).await;
+    let (status, _, body) = claim(&app, [10, 0, 0, 14], &destination(14).to_string()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let tx_id = body["transaction_id"].as_str().unwrap().to_string();
+
+    let block = RpcHash::from_u64_word(7);
+    mock.add_block(block, 500);
+    mock.notify(Notification::VirtualChainChanged(VirtualChainChangedNotification {
+        removed_chain_block_hashes: Arc::new(vec![]),
+        added_chain_block_hashes: Arc::new(vec![block]),
+        accepted_transaction_ids: Arc::new(vec![RpcAcceptedTransactionIds {
+            accepting_block_hash: block,
+            accepted_transaction_ids: vec![tx_id.parse().unwrap()],
+        }]),
+    }));
+    let body = wait_for_status(&app, &tx_id, "accepted").await;
+    assert_eq!(body["accepting_block_hash"], block.to_string());
+
+    // Short of the default confirmation depth of 10
+    mock.notify(Notification::SinkBlueScoreChanged(SinkBlueScoreChangedNotification { sink_blue_score: 505 }));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let body = claim_status(&app, &tx_id).await;
+    assert_eq!(body["status"], "accepted");
+    assert_eq!(body["confirmations"], 5);
+
+    mock.notify(Notification::SinkBlueScoreChanged(SinkBlueScoreChangedNotification { sink_blue_score: 510 }));
+    let body = wait_for_status(&app, &tx_id, "confirmed").await;
+    assert_eq!(body["confirmations"], 10);
+}
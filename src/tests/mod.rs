@@ -0,0 +1,68 @@
+//! Faucets running against `MockNode`, shared by the tests below.
+
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_consensus_core::network::{NetworkId, NetworkType};
+use std::sync::Arc;
+
+use crate::config::{ConsolidationConfig, FaucetConfig};
+use crate::mock::MockNode;
+use crate::rpc::DynNodeRpc;
+use crate::Faucet;
+
+mod builder;
+mod faucet;
+mod http;
+mod signing;
+mod tracker;
+mod utxo;
+
+const FAUCET_PRIVATE_KEY: &str = "b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef";
+const AMOUNT_PER_CLAIM: u64 = 100_000_000;
+
+fn network() -> NetworkId {
+    NetworkId::with_suffix(NetworkType::Testnet, 12)
+}
+
+fn config() -> FaucetConfig {
+    FaucetConfig {
+        network: network(),
+        faucet_private_key: FAUCET_PRIVATE_KEY.to_string(),
+        amount_per_claim: AMOUNT_PER_CLAIM,
+        ledger_path: ":memory:".to_string(),
+        batch_window_ms: 10,
+        consolidation: ConsolidationConfig {
+            enabled: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn destination(seed: u8) -> Address {
+    Address::new(Prefix::Testnet, Version::PubKey, &[seed; 32])
+}
+
+/// A faucet backed by a fresh mock node. `fund` sompi are given to the
+/// faucet address, in one UTXO, before it starts.
+async fn start_faucet(fund: Option<u64>, setup: impl FnOnce(&MockNode)) -> (Faucet, Arc<MockNode>) {
+    start_faucet_with(&config(), fund, setup).await
+}
+
+/// Like `start_faucet`, with `config` instead of the default test config.
+async fn start_faucet_with(
+    config: &FaucetConfig,
+    fund: Option<u64>,
+    setup: impl FnOnce(&MockNode),
+) -> (Faucet, Arc<MockNode>) {
+    let mock = MockNode::new(network());
+    let secret_key: secp256k1::SecretKey = FAUCET_PRIVATE_KEY.parse().unwrap();
+    let (x_only_public_key, _) = secp256k1::PublicKey::from_secret_key_global(&secret_key).x_only_public_key();
+    let faucet_address = Address::new(Prefix::Testnet, Version::PubKey, &x_only_public_key.serialize());
+    if let Some(amount) = fund {
+        mock.add_utxo(&faucet_address, amount);
+    }
+    setup(&mock);
+
+    let faucet = Faucet::new(config, Some(mock.clone() as DynNodeRpc)).await.unwrap();
+    (faucet, mock)
+}
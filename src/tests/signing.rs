@@ -0,0 +1,200 @@
+//! Builds, signs and script-verifies faucet transactions over random UTXO
+//! sets, so coin-selection, fee and change mistakes show up without a node.
+
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_consensus_core::{
+    hashing::sighash::SigHashReusedValuesUnsync,
+    sign::sign_with_multiple_v2,
+    tx::{
+        PopulatedTransaction, ScriptPublicKey, SignableTransaction, Transaction, TransactionId, TransactionOutpoint,
+        TransactionOutput, UtxoEntry, VerifiableTransaction,
+    },
+};
+use kaspa_txscript::{caches::Cache, standard::pay_to_address_script, TxScriptEngine};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::network;
+use crate::builder::{build_transaction, BuildError, UnsignedTransaction};
+use crate::coin_selection::CoinSelection;
+use crate::fees::{FeeCalculator, MAXIMUM_STANDARD_TRANSACTION_MASS};
+use crate::utxo::Utxo;
+
+const FAUCET_PRIVATE_KEY: [u8; 32] = [0x5a; 32];
+const SEEDS: u64 = 200;
+const SOMPI_PER_KAS: u64 = 100_000_000;
+
+const STRATEGIES: [CoinSelection; 4] = [
+    CoinSelection::LargestFirst,
+    CoinSelection::SmallestFirst,
+    CoinSelection::BranchAndBound,
+    CoinSelection::OldestFirst,
+];
+
+fn faucet_script() -> ScriptPublicKey {
+    let secret_key = secp256k1::SecretKey::from_slice(&FAUCET_PRIVATE_KEY).unwrap();
+    let (x_only_public_key, _) = secp256k1::PublicKey::from_secret_key_global(&secret_key).x_only_public_key();
+    pay_to_address_script(&Address::new(Prefix::Testnet, Version::PubKey, &x_only_public_key.serialize()))
+}
+
+/// A mix of dust-sized, claim-sized and large faucet UTXOs.
+fn random_utxos(rng: &mut StdRng, script: &ScriptPublicKey) -> Vec<Utxo> {
+    (0..rng.gen_range(1..=40u64))
+        .map(|i| {
+            let amount = match rng.gen_range(0..3) {
+                0 => rng.gen_range(1_000..SOMPI_PER_KAS / 100),
+                1 => rng.gen_range(SOMPI_PER_KAS / 100..10 * SOMPI_PER_KAS),
+                _ => rng.gen_range(10 * SOMPI_PER_KAS..10_000 * SOMPI_PER_KAS),
+            };
+            Utxo {
+                outpoint: TransactionOutpoint::new(TransactionId::from_u64_word(rng.gen()), i as u32),
+                entry: UtxoEntry::new(amount, script.clone(), rng.gen_range(0..1_000_000), false),
+            }
+        })
+        .collect()
+}
+
+/// Claim-sized payments, whose storage mass together stays well under the limit.
+fn random_payments(rng: &mut StdRng) -> Vec<TransactionOutput> {
+    (0..rng.gen_range(1..=5))
+        .map(|_| {
+            let destination = Address::new(Prefix::Testnet, Version::PubKey, &rng.gen::<[u8; 32]>());
+            TransactionOutput::new(rng.gen_range(SOMPI_PER_KAS..100 * SOMPI_PER_KAS), pay_to_address_script(&destination))
+        })
+        .collect()
+}
+
+/// Signs `unsigned` the way `sign_and_submit` does.
+fn sign(unsigned: &UnsignedTransaction) -> Transaction {
+    let signable_tx = SignableTransaction::with_entries(unsigned.tx.clone(), unsigned.entries.clone());
+    let signed_tx = sign_with_multiple_v2(signable_tx, std::slice::from_ref(&FAUCET_PRIVATE_KEY))
+        .fully_signed()
+        .expect("every input is the faucet's");
+    signed_tx.tx.as_ref().clone()
+}
+
+/// Runs every input's signature script against the `UtxoEntry` it spends.
+fn verify_scripts(tx: &Transaction, entries: &[UtxoEntry]) -> Result<(), String> {
+    let populated = PopulatedTransaction::new(tx, entries.to_vec());
+    let reused_values = SigHashReusedValuesUnsync::new();
+    let sig_cache = Cache::new(10_000);
+    for (index, (input, entry)) in populated.populated_inputs().enumerate() {
+        TxScriptEngine::from_transaction_input(&populated, input, index, entry, &reused_values, &sig_cache, true)
+            .execute()
+            .map_err(|e| format!("input {index} failed script validation: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Checks a signed transaction against what the builder claimed it built.
+fn check_transaction(
+    unsigned: &UnsignedTransaction,
+    signed: &Transaction,
+    payments: &[TransactionOutput],
+    change_script: &ScriptPublicKey,
+    fees: &FeeCalculator,
+) -> Result<(), String> {
+    verify_scripts(signed, &unsigned.entries)?;
+
+    let total_in: u64 = unsigned.entries.iter().map(|e| e.amount).sum();
+    let total_out: u64 = signed.outputs.iter().map(|o| o.value).sum();
+    if total_in != total_out + unsigned.fee {
+        return Err(format!("inputs {total_in} != outputs {total_out} + fee {}", unsigned.fee));
+    }
+
+    let mass = fees.mass(signed, &unsigned.entries);
+    if mass > MAXIMUM_STANDARD_TRANSACTION_MASS {
+        return Err(format!("signed mass {mass} is over the standard limit"));
+    }
+    if unsigned.fee < fees.fee(mass) {
+        return Err(format!("fee {} doesn't cover signed mass {mass}", unsigned.fee));
+    }
+
+    if signed.outputs[..payments.len()] != *payments {
+        return Err("payments were not paid first, in order and in full".to_string());
+    }
+    match (&unsigned.change, &signed.outputs[payments.len()..]) {
+        (None, []) => {}
+        (Some((index, output)), [change]) => {
+            if *index as usize != payments.len() || output != change || change.script_public_key != *change_script {
+                return Err(format!("change {:?} doesn't match output {change:?}", unsigned.change));
+            }
+        }
+        (change, extra) => return Err(format!("change {change:?} doesn't match extra outputs {extra:?}")),
+    }
+    Ok(())
+}
+
+#[test]
+fn selected_transactions_sign_and_balance() {
+    let change_script = faucet_script();
+    let (mut verified, mut too_large) = (0, 0);
+    for seed in 0..SEEDS {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let candidates = random_utxos(&mut rng, &change_script);
+        let mut payments = random_payments(&mut rng);
+        if rng.gen_ratio(1, 10) {
+            // A dust payment, over the storage mass limit on its own
+            payments[0].value = rng.gen_range(1_000..SOMPI_PER_KAS / 100);
+        }
+        let fees = FeeCalculator::new(network(), rng.gen_range(1.0..10.0));
+        let payments_mass: u64 = payments.iter().map(|p| fees.output_storage_mass(p.value)).sum();
+
+        for strategy in STRATEGIES {
+            let context = format!("seed {seed}, {strategy:?}");
+            let selected = match strategy.selector().select(candidates.clone(), &payments, &change_script, &fees) {
+                Ok(selected) => selected,
+                Err(BuildError::Insufficient { .. }) => {
+                    // Only acceptable if spending everything doesn't work either
+                    if let Ok(unsigned) = build_transaction(&candidates, &payments, &change_script, &fees) {
+                        panic!("{context}: reported insufficient funds, but all inputs pay with fee {}", unsigned.fee);
+                    }
+                    continue;
+                }
+                Err(BuildError::TooLarge { mass, .. }) => {
+                    // Only acceptable if the payments alone can't fit
+                    assert!(
+                        payments_mass > MAXIMUM_STANDARD_TRANSACTION_MASS,
+                        "{context}: reported mass {mass} over the limit, but the payments only carry {payments_mass}"
+                    );
+                    too_large += 1;
+                    continue;
+                }
+            };
+            for utxo in &selected {
+                assert!(candidates.iter().any(|c| c.outpoint == utxo.outpoint), "{context}: selected an unknown UTXO");
+            }
+
+            let unsigned = build_transaction(&selected, &payments, &change_script, &fees)
+                .unwrap_or_else(|e| panic!("{context}: selected inputs don't build: {e}"));
+            let signed = sign(&unsigned);
+            if let Err(e) = check_transaction(&unsigned, &signed, &payments, &change_script, &fees) {
+                panic!("{context}: {e}");
+            }
+            verified += 1;
+        }
+    }
+
+    // Skipped cases prove nothing, so most must get through to verification
+    let cases = SEEDS as usize * STRATEGIES.len();
+    assert!(verified >= cases / 2, "only {verified} of {cases} cases were verified");
+    assert!(too_large > 0, "no case exercised the mass limit");
+}
+
+/// The harness itself must catch a bad signature, or the test above proves nothing.
+#[test]
+fn tampered_signature_fails_verification() {
+    let change_script = faucet_script();
+    let mut rng = StdRng::seed_from_u64(0);
+    let candidates = vec![Utxo {
+        outpoint: TransactionOutpoint::new(TransactionId::from_u64_word(1), 0),
+        entry: UtxoEntry::new(1_000 * SOMPI_PER_KAS, change_script.clone(), 0, false),
+    }];
+    let payments = random_payments(&mut rng);
+    let fees = FeeCalculator::new(network(), 1.0);
+    let unsigned = build_transaction(&candidates, &payments, &change_script, &fees).unwrap();
+
+    let mut signed = sign(&unsigned);
+    assert_eq!(verify_scripts(&signed, &unsigned.entries), Ok(()));
+    signed.inputs[0].signature_script[10] ^= 1;
+    assert!(verify_scripts(&signed, &unsigned.entries).is_err());
+}
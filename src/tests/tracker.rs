@@ -0,0 +1,18 @@
+//! Claim tracking when node notifications and submission results race.
+
+use kaspa_rpc_core::{RpcHash, RpcTransactionId};
+
+use crate::tracker::{ClaimState, ClaimTracker};
+
+#[test]
+fn acceptance_before_the_submit_reply_is_kept() {
+    let tracker = ClaimTracker::new(10);
+    let tx_id = RpcTransactionId::from_u64_word(1);
+    tracker.submitting(tx_id, 0);
+
+    // VirtualChainChanged can land before submit_transaction returns
+    tracker.accept(RpcHash::from_u64_word(2), Some(100), Some(90), &[tx_id]);
+    tracker.submitted(tx_id);
+
+    assert_eq!(tracker.status(&tx_id).unwrap().status, ClaimState::Accepted);
+}
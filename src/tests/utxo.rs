@@ -0,0 +1,66 @@
+//! `UtxoManager` bookkeeping around our own unconfirmed transactions.
+
+use kaspa_consensus_core::tx::{ScriptPublicKey, TransactionId, TransactionOutpoint, UtxoEntry};
+
+use crate::utxo::{Utxo, UtxoManager, UNACCEPTED_DAA_SCORE};
+
+fn utxo(tx: u64, amount: u64, block_daa_score: u64) -> Utxo {
+    Utxo {
+        outpoint: TransactionOutpoint::new(TransactionId::from_u64_word(tx), 0),
+        entry: UtxoEntry::new(amount, ScriptPublicKey::from_vec(0, vec![]), block_daa_score, false),
+    }
+}
+
+fn selectable(manager: &UtxoManager) -> Vec<TransactionOutpoint> {
+    let reservation = manager.reserve(|spendable| Ok::<_, ()>(spendable)).unwrap();
+    reservation.utxos().iter().map(|u| u.outpoint).collect()
+}
+
+/// Commits a transaction spending everything selectable, with `change`.
+fn spend_all(manager: &UtxoManager, change: Utxo) {
+    manager.reserve(|spendable| Ok::<_, ()>(spendable)).unwrap().commit(Some(change));
+}
+
+#[test]
+fn spent_change_is_not_revived_when_the_node_reports_it() {
+    let manager = UtxoManager::new(1000);
+    let funding = utxo(1, 1_000_000, 10);
+    manager.sync(vec![funding.clone()]);
+
+    // tx1 spends the funding UTXO into change C, tx2 spends C into change D
+    let c = utxo(2, 900_000, UNACCEPTED_DAA_SCORE);
+    spend_all(&manager, c.clone());
+    assert_eq!(selectable(&manager), vec![c.outpoint]);
+    let d = utxo(3, 800_000, UNACCEPTED_DAA_SCORE);
+    spend_all(&manager, d.clone());
+
+    // The node reports tx1 before tx2 is accepted
+    let c_accepted = utxo(2, 900_000, 20);
+    manager.apply_changes(vec![c_accepted.clone()], vec![funding]);
+    assert_eq!(selectable(&manager), vec![d.outpoint]);
+
+    // Likewise for a full snapshot taken in between
+    manager.sync(vec![c_accepted.clone()]);
+    assert_eq!(selectable(&manager), vec![d.outpoint]);
+
+    // Once tx2 is accepted, C is gone and D is confirmed
+    manager.apply_changes(vec![utxo(3, 800_000, 21)], vec![c_accepted]);
+    assert_eq!(selectable(&manager), vec![d.outpoint]);
+}
+
+#[test]
+fn stale_snapshot_does_not_revive_a_spent_outpoint() {
+    let manager = UtxoManager::new(1000);
+    let funding = utxo(1, 1_000_000, 10);
+    manager.sync(vec![funding.clone()]);
+    let change = utxo(2, 900_000, UNACCEPTED_DAA_SCORE);
+    spend_all(&manager, change.clone());
+
+    // The spend is accepted while a resubscribe is fetching its snapshot,
+    // which was taken just before
+    manager.begin_sync();
+    manager.apply_changes(vec![utxo(2, 900_000, 20)], vec![funding.clone()]);
+    manager.sync(vec![funding]);
+
+    assert_eq!(selectable(&manager), vec![change.outpoint]);
+}
@@ -0,0 +1,419 @@
+use kaspa_rpc_core::{api::rpc::RpcApi, RpcHash, RpcTransactionId};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::ledger::unix_now;
+use crate::nodes::NodePool;
+use crate::rpc::NodeRpc;
+
+/// How often in-mempool claims are checked for having been dropped.
+const MEMPOOL_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Consecutive mempool misses (without an acceptance) before a claim is
+/// checked against the virtual chain and, if it isn't there, considered
+/// dropped. One miss can simply be a race with acceptance.
+const DROPPED_AFTER_MISSES: u8 = 2;
+
+/// Claims are forgotten this long after submission.
+const TRACKING_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Lifecycle events buffered for slow SSE subscribers.
+const EVENT_BUFFER: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimState {
+    Submitted,
+    InMempool,
+    Accepted,
+    Confirmed,
+    Dropped,
+    Rejected,
+}
+
+/// One step in a claim transaction's life, as streamed over SSE.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ClaimEvent {
+    Queued {
+        at: u64,
+    },
+    Signed {
+        at: u64,
+    },
+    Submitted {
+        at: u64,
+    },
+    InMempool {
+        at: u64,
+    },
+    Accepted {
+        at: u64,
+        block_hash: String,
+        daa_score: Option<u64>,
+        blue_score: Option<u64>,
+    },
+    Confirmed {
+        at: u64,
+        blue_score: u64,
+        confirmations: u64,
+    },
+    Dropped {
+        at: u64,
+    },
+    Rejected {
+        at: u64,
+        reason: String,
+    },
+}
+
+impl ClaimEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClaimEvent::Queued { .. } => "queued",
+            ClaimEvent::Signed { .. } => "signed",
+            ClaimEvent::Submitted { .. } => "submitted",
+            ClaimEvent::InMempool { .. } => "in_mempool",
+            ClaimEvent::Accepted { .. } => "accepted",
+            ClaimEvent::Confirmed { .. } => "confirmed",
+            ClaimEvent::Dropped { .. } => "dropped",
+            ClaimEvent::Rejected { .. } => "rejected",
+        }
+    }
+
+    /// No further events follow a terminal one.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            ClaimEvent::Confirmed { .. } | ClaimEvent::Dropped { .. } | ClaimEvent::Rejected { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaimStatus {
+    pub transaction_id: String,
+    pub status: ClaimState,
+    pub accepting_block_hash: Option<String>,
+    pub accepting_daa_score: Option<u64>,
+    pub confirmations: Option<u64>,
+}
+
+#[derive(Clone, Copy)]
+struct AcceptingBlock {
+    hash: RpcHash,
+    daa_score: Option<u64>,
+    blue_score: Option<u64>,
+}
+
+struct Tracked {
+    state: ClaimState,
+    submitted_at: Instant,
+    accepting_block: Option<AcceptingBlock>,
+    confirmations: Option<u64>,
+    mempool_misses: u8,
+    /// Chain block that was the sink when the claim was submitted; its
+    /// acceptance, if notifications missed it, is searched for from here.
+    chain_start: RpcHash,
+    events: Vec<ClaimEvent>,
+}
+
+/// Follows submitted claim transactions from signing, through the mempool,
+/// into the virtual chain and up to `confirmation_depth` blue score, driven
+/// by `VirtualChainChanged` and `SinkBlueScoreChanged` notifications.
+pub struct ClaimTracker {
+    claims: Mutex<HashMap<RpcTransactionId, Tracked>>,
+    events: broadcast::Sender<(RpcTransactionId, ClaimEvent)>,
+    sink: Mutex<RpcHash>,
+    confirmation_depth: u64,
+}
+
+impl ClaimTracker {
+    pub fn new(confirmation_depth: u64) -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Self {
+            claims: Mutex::new(HashMap::new()),
+            events,
+            sink: Mutex::new(RpcHash::default()),
+            confirmation_depth,
+        }
+    }
+
+    /// Records the node's current sink, as seen at subscription or in the
+    /// latest `VirtualChainChanged`.
+    pub fn set_sink(&self, sink: RpcHash) {
+        *self.sink.lock().unwrap() = sink;
+    }
+
+    /// Starts tracking a claim transaction that has been signed and is about
+    /// to be handed to the node. `queued_at` is when its batch started filling.
+    pub fn submitting(&self, tx_id: RpcTransactionId, queued_at: u64) {
+        let mut claims = self.claims.lock().unwrap();
+        claims.retain(|_, c| c.submitted_at.elapsed() < TRACKING_TTL);
+        claims.insert(
+            tx_id,
+            Tracked {
+                state: ClaimState::Submitted,
+                submitted_at: Instant::now(),
+                accepting_block: None,
+                confirmations: None,
+                mempool_misses: 0,
+                chain_start: *self.sink.lock().unwrap(),
+                events: Vec::new(),
+            },
+        );
+        let now = unix_now();
+        self.emit(&mut claims, tx_id, ClaimEvent::Queued { at: queued_at });
+        self.emit(&mut claims, tx_id, ClaimEvent::Signed { at: now });
+        self.emit(&mut claims, tx_id, ClaimEvent::Submitted { at: now });
+    }
+
+    /// The node accepted the transaction into its mempool. A notification may
+    /// already have moved it further, which this must not undo.
+    pub fn submitted(&self, tx_id: RpcTransactionId) {
+        let mut claims = self.claims.lock().unwrap();
+        if let Some(claim) = claims.get_mut(&tx_id).filter(|c| c.state == ClaimState::Submitted) {
+            claim.state = ClaimState::InMempool;
+            self.emit(&mut claims, tx_id, ClaimEvent::InMempool { at: unix_now() });
+        }
+    }
+
+    /// The node refused the transaction.
+    pub fn rejected(&self, tx_id: RpcTransactionId, reason: String) {
+        let mut claims = self.claims.lock().unwrap();
+        if let Some(claim) = claims.get_mut(&tx_id) {
+            claim.state = ClaimState::Rejected;
+            self.emit(&mut claims, tx_id, ClaimEvent::Rejected { at: unix_now(), reason });
+        }
+    }
+
+    pub fn status(&self, tx_id: &RpcTransactionId) -> Option<ClaimStatus> {
+        let claims = self.claims.lock().unwrap();
+        claims.get(tx_id).map(|c| ClaimStatus {
+            transaction_id: tx_id.to_string(),
+            status: c.state,
+            accepting_block_hash: c.accepting_block.map(|b| b.hash.to_string()),
+            accepting_daa_score: c.accepting_block.and_then(|b| b.daa_score),
+            confirmations: c.confirmations,
+        })
+    }
+
+    /// Events so far for `tx_id` plus a receiver for the ones to come. Taken
+    /// under one lock so nothing falls between history and live events.
+    pub fn subscribe(
+        &self,
+        tx_id: &RpcTransactionId,
+    ) -> Option<(Vec<ClaimEvent>, broadcast::Receiver<(RpcTransactionId, ClaimEvent)>)> {
+        let claims = self.claims.lock().unwrap();
+        claims
+            .get(tx_id)
+            .map(|c| (c.events.clone(), self.events.subscribe()))
+    }
+
+    /// The subset of `tx_ids` that belongs to tracked claims.
+    pub fn tracked(&self, tx_ids: &[RpcTransactionId]) -> Vec<RpcTransactionId> {
+        let claims = self.claims.lock().unwrap();
+        tx_ids
+            .iter()
+            .filter(|id| claims.contains_key(*id))
+            .copied()
+            .collect()
+    }
+
+    pub fn accept(
+        &self,
+        block_hash: RpcHash,
+        daa_score: Option<u64>,
+        blue_score: Option<u64>,
+        tx_ids: &[RpcTransactionId],
+    ) {
+        let mut claims = self.claims.lock().unwrap();
+        for tx_id in tx_ids {
+            let Some(claim) = claims.get_mut(tx_id) else {
+                continue;
+            };
+            if matches!(claim.state, ClaimState::Confirmed | ClaimState::Rejected) {
+                continue;
+            }
+            info!("Claim {} accepted by block {}", tx_id, block_hash);
+            claim.state = ClaimState::Accepted;
+            claim.accepting_block = Some(AcceptingBlock {
+                hash: block_hash,
+                daa_score,
+                blue_score,
+            });
+            let event = ClaimEvent::Accepted {
+                at: unix_now(),
+                block_hash: block_hash.to_string(),
+                daa_score,
+                blue_score,
+            };
+            self.emit(&mut claims, *tx_id, event);
+        }
+    }
+
+    /// Handles chain blocks removed by a reorg: their claims go back to the mempool.
+    pub fn unaccept(&self, removed_block_hashes: &[RpcHash]) {
+        let mut claims = self.claims.lock().unwrap();
+        let reverted = claims
+            .iter()
+            .filter(|(_, c)| c.state == ClaimState::Accepted)
+            .filter(|(_, c)| {
+                c.accepting_block
+                    .is_some_and(|b| removed_block_hashes.contains(&b.hash))
+            })
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for tx_id in reverted {
+            if let Some(claim) = claims.get_mut(&tx_id) {
+                claim.state = ClaimState::InMempool;
+                claim.accepting_block = None;
+                claim.confirmations = None;
+                claim.mempool_misses = 0;
+            }
+            self.emit(&mut claims, tx_id, ClaimEvent::InMempool { at: unix_now() });
+        }
+    }
+
+    /// Updates confirmation counts from the sink's blue score and emits
+    /// `confirmed` for claims that reached `confirmation_depth`.
+    pub fn sink_blue_score_changed(&self, sink_blue_score: u64) {
+        let mut claims = self.claims.lock().unwrap();
+        let mut confirmed = Vec::new();
+        for (tx_id, claim) in claims.iter_mut() {
+            if claim.state != ClaimState::Accepted {
+                continue;
+            }
+            let Some(accepted_at) = claim.accepting_block.and_then(|b| b.blue_score) else {
+                continue;
+            };
+            let confirmations = sink_blue_score.saturating_sub(accepted_at);
+            claim.confirmations = Some(confirmations);
+            if confirmations >= self.confirmation_depth {
+                claim.state = ClaimState::Confirmed;
+                confirmed.push((*tx_id, confirmations));
+            }
+        }
+        for (tx_id, confirmations) in confirmed {
+            let event = ClaimEvent::Confirmed {
+                at: unix_now(),
+                blue_score: sink_blue_score,
+                confirmations,
+            };
+            self.emit(&mut claims, tx_id, event);
+        }
+    }
+
+    fn in_mempool(&self) -> Vec<RpcTransactionId> {
+        let claims = self.claims.lock().unwrap();
+        claims
+            .iter()
+            .filter(|(_, c)| c.state == ClaimState::InMempool)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Counts a mempool miss. Returns the chain block to search for the
+    /// claim's acceptance from once it has missed too often.
+    fn record_mempool_check(&self, tx_id: &RpcTransactionId, found: bool) -> Option<RpcHash> {
+        let mut claims = self.claims.lock().unwrap();
+        let claim = claims.get_mut(tx_id)?;
+        if claim.state != ClaimState::InMempool {
+            return None;
+        }
+        if found {
+            claim.mempool_misses = 0;
+            return None;
+        }
+        claim.mempool_misses = claim.mempool_misses.saturating_add(1);
+        (claim.mempool_misses >= DROPPED_AFTER_MISSES).then_some(claim.chain_start)
+    }
+
+    /// Marks a claim that is neither in the mempool nor in the chain as dropped.
+    fn dropped(&self, tx_id: &RpcTransactionId) {
+        let mut claims = self.claims.lock().unwrap();
+        let Some(claim) = claims.get_mut(tx_id) else {
+            return;
+        };
+        if claim.state != ClaimState::InMempool {
+            return;
+        }
+        info!("Claim {} was dropped from the mempool", tx_id);
+        claim.state = ClaimState::Dropped;
+        self.emit(&mut claims, *tx_id, ClaimEvent::Dropped { at: unix_now() });
+    }
+
+    /// Appends `event` to the claim's history and broadcasts it. Callers hold
+    /// the claims lock, which keeps history and broadcast order consistent.
+    fn emit(
+        &self,
+        claims: &mut HashMap<RpcTransactionId, Tracked>,
+        tx_id: RpcTransactionId,
+        event: ClaimEvent,
+    ) {
+        if let Some(claim) = claims.get_mut(&tx_id) {
+            claim.events.push(event.clone());
+        }
+        // No subscribers is fine
+        let _ = self.events.send((tx_id, event));
+    }
+}
+
+/// Periodically asks the node whether in-mempool claims are still there, to
+/// notice transactions that were evicted without ever being accepted. A claim
+/// missing from the mempool is looked for in the virtual chain first, since
+/// its acceptance notification is lost if it came during a reconnect.
+pub fn spawn_mempool_check(nodes: Arc<NodePool>, tracker: Arc<ClaimTracker>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(MEMPOOL_CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let client = nodes.client();
+            if !client.is_connected() {
+                continue;
+            }
+            for tx_id in tracker.in_mempool() {
+                let found = client.get_mempool_entry(tx_id, true, false).await.is_ok();
+                let Some(chain_start) = tracker.record_mempool_check(&tx_id, found) else {
+                    continue;
+                };
+                match accepting_block(client.as_ref(), chain_start, tx_id).await {
+                    Ok(Some(block)) => {
+                        tracker.accept(block.hash, block.daa_score, block.blue_score, &[tx_id]);
+                    }
+                    Ok(None) => tracker.dropped(&tx_id),
+                    // Retried on the next round
+                    Err(e) => warn!("Failed to look for claim {} in the virtual chain: {e}", tx_id),
+                }
+            }
+        }
+    })
+}
+
+/// The chain block that accepted `tx_id` since `chain_start`, if any.
+async fn accepting_block(
+    client: &dyn NodeRpc,
+    chain_start: RpcHash,
+    tx_id: RpcTransactionId,
+) -> anyhow::Result<Option<AcceptingBlock>> {
+    let chain = client.get_virtual_chain_from_block(chain_start, true, None).await?;
+    let Some(hash) = chain
+        .accepted_transaction_ids
+        .iter()
+        .find(|a| a.accepted_transaction_ids.contains(&tx_id))
+        .map(|a| a.accepting_block_hash)
+    else {
+        return Ok(None);
+    };
+    let header = client.get_block(hash, false).await?.header;
+    Ok(Some(AcceptingBlock {
+        hash,
+        daa_score: Some(header.daa_score),
+        blue_score: Some(header.blue_score),
+    }))
+}
@@ -0,0 +1,233 @@
+use kaspa_consensus_core::tx::{TransactionOutpoint, UtxoEntry};
+use kaspa_rpc_core::RpcUtxosByAddressesEntry;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// DAA score given to our own change outputs until the node reports them.
+pub const UNACCEPTED_DAA_SCORE: u64 = u64::MAX;
+
+/// How long we trust local bookkeeping about a submitted transaction before
+/// deferring to whatever the node reports. Covers transactions that were
+/// accepted into the mempool but later evicted.
+const PENDING_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone)]
+pub struct Utxo {
+    pub outpoint: TransactionOutpoint,
+    pub entry: UtxoEntry,
+}
+
+impl From<RpcUtxosByAddressesEntry> for Utxo {
+    fn from(e: RpcUtxosByAddressesEntry) -> Self {
+        Self {
+            outpoint: e.outpoint.into(),
+            entry: e.utxo_entry.into(),
+        }
+    }
+}
+
+/// Whether `utxo` can be spent at `virtual_daa_score`. Coinbase outputs
+/// need `coinbase_maturity` DAA score on top of the block that created them.
+pub fn is_mature(utxo: &Utxo, virtual_daa_score: u64, coinbase_maturity: u64) -> bool {
+    !utxo.entry.is_coinbase
+        || utxo.entry.block_daa_score.saturating_add(coinbase_maturity) <= virtual_daa_score
+}
+
+/// Faucet balance split by coinbase maturity, in sompi.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Balances {
+    pub spendable: u64,
+    pub immature: u64,
+}
+
+/// Tracks the faucet's UTXOs and which of them are already committed to a
+/// transaction, so concurrent claims never select the same outpoint.
+pub struct UtxoManager {
+    inner: Mutex<Inner>,
+    coinbase_maturity: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// Latest virtual DAA score seen from the node, for coinbase maturity.
+    virtual_daa_score: u64,
+    /// UTXOs as last reported by the node.
+    confirmed: HashMap<TransactionOutpoint, UtxoEntry>,
+    /// Change outputs of our own submitted transactions, not yet reported by the node.
+    unconfirmed: HashMap<TransactionOutpoint, (UtxoEntry, Instant)>,
+    /// Outpoints selected by a transaction that is being built or submitted.
+    reserved: HashSet<TransactionOutpoint>,
+    /// Outpoints spent by a submitted transaction that the node may still report
+    /// as unspent, kept for `PENDING_TTL` whatever the node says meanwhile.
+    spent: HashMap<TransactionOutpoint, Instant>,
+    /// Changes applied since `begin_sync`, replayed over the snapshot that
+    /// `sync` installs since it may predate them.
+    pending_changes: Option<Vec<(Vec<Utxo>, Vec<Utxo>)>>,
+}
+
+/// Outpoints held for one transaction. Dropping it without calling
+/// [`Reservation::commit`] hands the outpoints back, which is what happens
+/// when building or submitting the transaction fails.
+pub struct Reservation<'a> {
+    manager: &'a UtxoManager,
+    utxos: Vec<Utxo>,
+}
+
+impl UtxoManager {
+    pub fn new(coinbase_maturity: u64) -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+            coinbase_maturity,
+        }
+    }
+
+    pub fn set_virtual_daa_score(&self, virtual_daa_score: u64) {
+        self.inner.lock().unwrap().virtual_daa_score = virtual_daa_score;
+    }
+
+    /// Starts recording notifications for the snapshot about to be fetched.
+    /// Call once subscribed, before asking the node for its UTXOs.
+    pub fn begin_sync(&self) {
+        self.inner.lock().unwrap().pending_changes = Some(Vec::new());
+    }
+
+    /// Replaces the confirmed set with a fresh snapshot from the node, then
+    /// reapplies whatever notifications arrived while it was being fetched.
+    pub fn sync(&self, utxos: Vec<Utxo>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.confirmed = utxos.into_iter().map(|u| (u.outpoint, u.entry)).collect();
+        for (added, removed) in inner.pending_changes.take().unwrap_or_default() {
+            inner.apply(added, removed);
+        }
+        inner.prune();
+    }
+
+    /// Applies a `UtxosChanged` notification for the faucet address.
+    pub fn apply_changes(&self, added: Vec<Utxo>, removed: Vec<Utxo>) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(pending) = &mut inner.pending_changes {
+            pending.push((added.clone(), removed.clone()));
+        }
+        inner.apply(added, removed);
+        inner.prune();
+    }
+
+    /// Value owned by the faucet, counting unconfirmed change and excluding
+    /// outputs already spent by a submitted transaction.
+    pub fn balances(&self) -> Balances {
+        let inner = self.inner.lock().unwrap();
+        let mut balances = Balances::default();
+        for utxo in inner.owned() {
+            if is_mature(&utxo, inner.virtual_daa_score, self.coinbase_maturity) {
+                balances.spendable += utxo.entry.amount;
+            } else {
+                balances.immature += utxo.entry.amount;
+            }
+        }
+        balances
+    }
+
+    /// Everything `balances` counts, with whether each UTXO is mature.
+    pub fn utxos(&self) -> Vec<(Utxo, bool)> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .owned()
+            .map(|utxo| {
+                let mature = is_mature(&utxo, inner.virtual_daa_score, self.coinbase_maturity);
+                (utxo, mature)
+            })
+            .collect()
+    }
+
+    /// Runs `select` over the spendable UTXOs and reserves whatever it picks.
+    /// Selection happens under the lock, so no two callers can pick the same outpoint.
+    pub fn reserve<E>(
+        &self,
+        select: impl FnOnce(Vec<Utxo>) -> Result<Vec<Utxo>, E>,
+    ) -> Result<Reservation<'_>, E> {
+        let mut inner = self.inner.lock().unwrap();
+        let selected = select(inner.spendable(self.coinbase_maturity))?;
+        inner.reserved.extend(selected.iter().map(|u| u.outpoint));
+        Ok(Reservation {
+            manager: self,
+            utxos: selected,
+        })
+    }
+}
+
+impl Inner {
+    fn apply(&mut self, added: Vec<Utxo>, removed: Vec<Utxo>) {
+        for utxo in removed {
+            // Stays in `spent`: a snapshot taken before this removal may still list it
+            self.confirmed.remove(&utxo.outpoint);
+        }
+        for utxo in added {
+            self.unconfirmed.remove(&utxo.outpoint);
+            self.confirmed.insert(utxo.outpoint, utxo.entry);
+        }
+    }
+
+    fn prune(&mut self) {
+        let Inner {
+            confirmed,
+            unconfirmed,
+            spent,
+            ..
+        } = self;
+        // Kept until the TTL runs out, even once the node reports the removal
+        // or while absent from `confirmed`: our own unconfirmed change may
+        // still be reported as added, or a stale snapshot list it, after we spent it.
+        spent.retain(|_, at| at.elapsed() < PENDING_TTL);
+        unconfirmed.retain(|outpoint, (_, at)| !confirmed.contains_key(outpoint) && at.elapsed() < PENDING_TTL);
+    }
+
+    /// Everything we own that isn't already spent by a submitted transaction.
+    fn owned(&self) -> impl Iterator<Item = Utxo> + '_ {
+        let unconfirmed = self.unconfirmed.iter().map(|(o, (e, _))| (o, e));
+        self.confirmed
+            .iter()
+            .chain(unconfirmed)
+            .filter(|(o, _)| !self.spent.contains_key(o))
+            .map(|(o, e)| Utxo {
+                outpoint: *o,
+                entry: e.clone(),
+            })
+    }
+
+    fn spendable(&self, coinbase_maturity: u64) -> Vec<Utxo> {
+        self.owned()
+            .filter(|u| !self.reserved.contains(&u.outpoint))
+            .filter(|u| is_mature(u, self.virtual_daa_score, coinbase_maturity))
+            .collect()
+    }
+}
+
+impl Reservation<'_> {
+    pub fn utxos(&self) -> &[Utxo] {
+        &self.utxos
+    }
+
+    /// Marks the reserved outpoints as spent by an accepted transaction and
+    /// makes its change output (if any) immediately spendable.
+    pub fn commit(self, change: Option<Utxo>) {
+        let mut inner = self.manager.inner.lock().unwrap();
+        let now = Instant::now();
+        for utxo in &self.utxos {
+            inner.unconfirmed.remove(&utxo.outpoint);
+            inner.spent.insert(utxo.outpoint, now);
+        }
+        if let Some(change) = change {
+            inner.unconfirmed.insert(change.outpoint, (change.entry, now));
+        }
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        let mut inner = self.manager.inner.lock().unwrap();
+        for utxo in &self.utxos {
+            inner.reserved.remove(&utxo.outpoint);
+        }
+    }
+}